@@ -2,6 +2,7 @@
 #![allow(non_camel_case_types)]
 #![allow(non_snake_case)]
 
+use std::os::raw::c_void;
 use std::ptr;
 
 use crate::*;
@@ -15,6 +16,9 @@ lazy_static! {
     };
 }
 
+make_static_instance!(Descriptor, GrB_DESC_RC, replace_complement_mask);
+make_static_instance!(Descriptor, GrB_DESC_T0, transpose_first);
+
 impl dyn Descriptor {
     pub fn default() -> &'static StaticDescriptor {
         &default_descriptor
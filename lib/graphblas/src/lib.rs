@@ -9,6 +9,7 @@ pub use crate::monoid::*;
 pub use crate::semiring::*;
 pub use crate::types::*;
 pub use crate::unary_op::*;
+pub use crate::vector::*;
 
 mod binary_op;
 mod descriptor;
@@ -18,6 +19,7 @@ mod monoid;
 mod semiring;
 mod types;
 mod unary_op;
+mod vector;
 
 #[test]
 fn matrices_test() {
@@ -88,6 +88,17 @@ pub trait MatrixActions<T> {
         a: &M,
     );
 
+    /// Applies `op` to the entries of `a` allowed by `mask` as `desc` interprets it.
+    fn assign_apply_masked<X, O: UnaryOp<X, T>, M: Matrix<X>, K: Matrix<bool>, D: Descriptor>(
+        &mut self,
+        mask: &K,
+        op: &O,
+        a: &M,
+        desc: &D,
+    );
+
+    fn assign_transpose<M: Matrix<T>>(&mut self, a: &M);
+
     fn assign_kronecker<S: Semiring<T>>(&mut self, semiring: &S, a: &Self, b: &Self);
 
     fn accumulate_kronecker<X, S: Semiring<X>, A: BinaryOp<T, X, T>, M: Matrix<X>>(
@@ -177,6 +188,35 @@ impl<T, MT: Matrix<T>> MatrixActions<T> for MT {
         );
     }
 
+    fn assign_apply_masked<X, O: UnaryOp<X, T>, M: Matrix<X>, K: Matrix<bool>, D: Descriptor>(
+        &mut self,
+        mask: &K,
+        op: &O,
+        a: &M,
+        desc: &D,
+    ) {
+        grb_run!(
+            GrB_Matrix_apply,
+            self.grb_link_mut().link_mut(),
+            mask.grb_link().link(),
+            ptr::null_mut(),
+            op.grb_link().link(),
+            a.grb_link().link(),
+            desc.grb_link().link()
+        );
+    }
+
+    fn assign_transpose<M: Matrix<T>>(&mut self, a: &M) {
+        grb_run!(
+            GrB_transpose,
+            self.grb_link_mut().link_mut(),
+            ptr::null_mut(),
+            ptr::null_mut(),
+            a.grb_link().link(),
+            ptr::null_mut()
+        );
+    }
+
     fn assign_kronecker<S: Semiring<T>>(&mut self, semiring: &S, a: &Self, b: &Self) {
         grb_run!(
             GrB_Matrix_kronecker_Semiring,
@@ -459,6 +499,14 @@ extern "C" {
         desc: *const c_void,
     ) -> u32;
 
+    fn GrB_transpose(
+        C: *mut c_void,
+        Mask: *const c_void,
+        accum: *const c_void,
+        A: *const c_void,
+        desc: *const c_void,
+    ) -> u32;
+
     fn GrB_Matrix_kronecker_Semiring(
         C: *mut c_void,
         Mask: *const c_void,
@@ -20,3 +20,4 @@ macro_rules! make_monoid {
 
 make_monoid!(bool, BOOL, LOR, lor);
 make_monoid!(bool, BOOL, LAND, land);
+make_monoid!(u32, UINT32, PLUS, plus);
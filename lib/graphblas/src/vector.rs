@@ -0,0 +1,162 @@
+#![allow(non_upper_case_globals)]
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use std::marker::PhantomData;
+use std::mem::MaybeUninit;
+use std::os::raw::c_void;
+use std::ptr;
+
+use crate::*;
+
+make_ffi_trait!(Vector<T>);
+
+pub struct BaseTypeVector<T> {
+    link: GrbLink,
+    _T: PhantomData<*const T>,
+}
+
+impl<T> Vector<T> for BaseTypeVector<T> {
+    fn grb_link(&self) -> &GrbLink {
+        &self.link
+    }
+
+    fn grb_link_mut(&mut self) -> &mut GrbLink {
+        &mut self.link
+    }
+}
+
+impl<T: 'static + GrbType<T>> dyn Vector<T> {
+    pub fn new(size: u64) -> BaseTypeVector<T> {
+        let vec = grb_call!(
+            GrB_Vector_new,
+            *mut c_void,
+            T::grb_type().grb_link().link(),
+            size
+        );
+
+        BaseTypeVector {
+            link: GrbLink::of(vec),
+            _T: PhantomData,
+        }
+    }
+}
+
+pub trait VectorActions<T> {
+    fn size(&self) -> u64;
+    fn nvals(&self) -> u64;
+
+    /// Reduces every row of `a` with `monoid`, the columns if `desc` transposes the input.
+    /// Empty rows give no entry.
+    fn assign_reduce<X, Mo: Monoid<T>, M: Matrix<X>, D: Descriptor>(
+        &mut self,
+        monoid: &Mo,
+        a: &M,
+        desc: &D,
+    );
+}
+
+impl<T, VT: Vector<T>> VectorActions<T> for VT {
+    fn size(&self) -> u64 {
+        grb_call!(GrB_Vector_size, u64, self.grb_link().link())
+    }
+
+    fn nvals(&self) -> u64 {
+        grb_call!(GrB_Vector_nvals, u64, self.grb_link().link())
+    }
+
+    fn assign_reduce<X, Mo: Monoid<T>, M: Matrix<X>, D: Descriptor>(
+        &mut self,
+        monoid: &Mo,
+        a: &M,
+        desc: &D,
+    ) {
+        grb_run!(
+            GrB_Matrix_reduce_Monoid,
+            self.grb_link_mut().link_mut(),
+            ptr::null_mut(),
+            ptr::null_mut(),
+            monoid.grb_link().link(),
+            a.grb_link().link(),
+            desc.grb_link().link()
+        );
+    }
+}
+
+impl<T> Drop for BaseTypeVector<T> {
+    fn drop(&mut self) {
+        grb_run!(GrB_Vector_free, &mut self.link.link_mut());
+    }
+}
+
+pub trait VectorExtractTuples<T> {
+    fn extract_tuples(&self) -> (Vec<u64>, Vec<T>);
+}
+
+macro_rules! vector_extract_impl {
+    ( $ty:ty, $grb_ty:ident ) => {
+        paste::paste! {
+            _vector_extract_impl!($ty, [<GrB_Vector_extractTuples_ $grb_ty>]);
+        }
+    };
+}
+
+macro_rules! _vector_extract_impl {
+    ( $typ:ty, $extract_elem_func:ident ) => {
+        extern "C" {
+            fn $extract_elem_func(
+                I: *mut u64,
+                X: *mut $typ,
+                nvals: *mut u64,
+                v: *const c_void,
+            ) -> u32;
+        }
+
+        impl<V: Vector<$typ>> VectorExtractTuples<$typ> for V {
+            fn extract_tuples(&self) -> (Vec<u64>, Vec<$typ>) {
+                let size = self.nvals();
+                let mut is = Vec::with_capacity(size as usize);
+                let mut vs = Vec::with_capacity(size as usize);
+
+                let mut nvals = size;
+
+                grb_run!(
+                    $extract_elem_func,
+                    is.as_mut_ptr(),
+                    vs.as_mut_ptr(),
+                    &mut nvals,
+                    self.grb_link().link()
+                );
+
+                unsafe {
+                    is.set_len(nvals as usize);
+                    vs.set_len(nvals as usize);
+                }
+
+                (is, vs)
+            }
+        }
+    };
+}
+
+for_each_type!(vector_extract_impl);
+
+#[link(name = "graphblas")]
+extern "C" {
+    fn GrB_Vector_new(v: *mut *mut c_void, type_: *const c_void, n: u64) -> u32;
+
+    fn GrB_Vector_size(n: *mut u64, v: *const c_void) -> u32;
+
+    fn GrB_Vector_nvals(nvals: *mut u64, v: *const c_void) -> u32;
+
+    fn GrB_Vector_free(v: *mut *mut c_void) -> u32;
+
+    fn GrB_Matrix_reduce_Monoid(
+        w: *mut c_void,
+        mask: *const c_void,
+        accum: *const c_void,
+        monoid: *const c_void,
+        A: *const c_void,
+        desc: *const c_void,
+    ) -> u32;
+}
@@ -1,15 +1,14 @@
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::io::{BufRead, Write};
 use std::path::Path;
-use std::str::from_utf8;
 
 use anyhow::Result;
-use pyo3::Python;
-use pyo3::types::PyModule;
 
 use graphblas::*;
 
+use super::cnf::read_cfg_in_cnf;
 use super::graph::{BooleanMatrix, Ends, ExtractPairs, Graph};
+use super::limits::{ExecutionLimits, LimitExceeded};
 
 #[derive(Debug)]
 pub struct ContextFreeGrammar {
@@ -44,17 +43,7 @@ impl ContextFreeGrammar {
     }
 
     pub fn _from_text(text: &str) -> Result<ContextFreeGrammar> {
-        let (initial, nonterminals, productions, produces_epsilon) = Python::with_gil(
-            |py| -> Result<(String, Vec<String>, Vec<(String, Vec<String>)>, bool)> {
-                let module = PyModule::from_code(
-                    py,
-                    from_utf8(include_bytes!("py/read_cfg_in_cnf.py"))?,
-                    "a.compute.py",
-                    "a",
-                )?;
-                Ok(module.call1("parse_cfg", (text,))?.extract()?)
-            },
-        )?;
+        let (initial, nonterminals, productions, produces_epsilon) = read_cfg_in_cnf(text)?;
 
         let mut unit_from_variable = HashMap::<String, HashSet<String>>::new();
         let mut pair_from_variable = HashMap::<String, HashMap<String, HashSet<String>>>::new();
@@ -158,7 +147,13 @@ impl ContextFreeGrammar {
 }
 
 impl Graph {
-    pub fn cfpq_hellings<'a>(&self, cfg: &'a ContextFreeGrammar) -> ResultWithSets<'a> {
+    /// Each pair taken from the worklist is an iteration of `limits`.
+    pub fn cfpq_hellings<'a>(
+        &self,
+        cfg: &'a ContextFreeGrammar,
+        limits: &ExecutionLimits,
+    ) -> Result<ResultWithSets<'a>, LimitExceeded> {
+        let mut progress = limits.start("Hellings' algorithm");
         let mut r = HashMap::<&str, HashSet<Ends>>::new();
         let mut m = VecDeque::<(Ends, &str)>::new();
 
@@ -188,6 +183,8 @@ impl Graph {
         }
 
         while !m.is_empty() {
+            let largest = r.values().map(HashSet::len).max().unwrap_or(0);
+            progress.iteration(largest as u64)?;
             let ((v, u), n_i) = m.pop_front().unwrap();
             let mut new = HashSet::<(Ends, &str)>::new();
 
@@ -232,13 +229,18 @@ impl Graph {
             }
         }
 
-        ResultWithSets {
+        Ok(ResultWithSets {
             map: r,
             nonterminals: &cfg.nonterminals,
-        }
+        })
     }
 
-    pub fn cfpq_matrix_product<'a>(&self, cfg: &'a ContextFreeGrammar) -> ResultWithMatrices<'a> {
+    pub fn cfpq_matrix_product<'a>(
+        &self,
+        cfg: &'a ContextFreeGrammar,
+        limits: &ExecutionLimits,
+    ) -> Result<ResultWithMatrices<'a>, LimitExceeded> {
+        let mut progress = limits.start("matrix product CFPQ");
         let mut matrices = HashMap::<&str, BooleanMatrix>::new();
         for (body, heads) in &cfg.unit_from_variable {
             if let Some(body_matrix) = self.matrices.get(body) {
@@ -264,6 +266,8 @@ impl Graph {
 
         let mut changing = true;
         while changing {
+            let largest = matrices.values().map(|m| m.nvals()).max().unwrap_or(0);
+            progress.iteration(largest)?;
             changing = false;
             for (left, map) in &cfg.pair_from_variable {
                 for (right, heads) in map {
@@ -323,10 +327,10 @@ impl Graph {
             }
         }
 
-        ResultWithMatrices {
+        Ok(ResultWithMatrices {
             map: matrices,
             nonterminals: &cfg.nonterminals,
-        }
+        })
     }
 }
 
@@ -433,6 +437,7 @@ mod cfpq {
     use anyhow::Result;
 
     use crate::compute::graph::{Ends, Graph};
+    use crate::compute::limits::ExecutionLimits;
     use crate::compute::rfa::Rfa;
 
     use super::{ContextFreeGrammar, ContextFreeResult};
@@ -467,17 +472,23 @@ mod cfpq {
 
     fn hellings(text: &str, graph: &Graph) -> Result<Vec<Ends>> {
         let grammar = ContextFreeGrammar::from_text(text)?;
-        Ok(graph.cfpq_hellings(&grammar).reachable_edges("S"))
+        Ok(graph
+            .cfpq_hellings(&grammar, &ExecutionLimits::default())?
+            .reachable_edges("S"))
     }
 
     fn matrices(text: &str, graph: &Graph) -> Result<Vec<Ends>> {
         let grammar = ContextFreeGrammar::from_text(text)?;
-        Ok(graph.cfpq_matrix_product(&grammar).reachable_edges("S"))
+        Ok(graph
+            .cfpq_matrix_product(&grammar, &ExecutionLimits::default())?
+            .reachable_edges("S"))
     }
 
     fn tensors(text: &str, graph: &Graph) -> Result<Vec<Ends>> {
         let rfa = Rfa::from_text(text)?;
-        Ok(graph.cfpq_tensor_product(&rfa).reachable_edges("S"))
+        Ok(graph
+            .cfpq_tensor_product(&rfa, &ExecutionLimits::default())?
+            .reachable_edges("S"))
     }
 
     test!(hellings, "graph1", "epsilon", &[(0, 0), (1, 1)]);
@@ -0,0 +1,335 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::{Error, Result};
+
+/// Start symbol, variables of the grammar as written, productions in Chomsky normal form
+/// and whether the start symbol produces the empty word.
+pub type NormalForm = (String, Vec<String>, Vec<(String, Vec<String>)>, bool);
+
+const EPSILON_SYMBOLS: &[&str] = &["epsilon", "$", "ε", "ϵ", "Є"];
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Symbol {
+    Variable(String),
+    Terminal(String),
+}
+
+type Production = (String, Vec<Symbol>);
+
+/// Context-free grammar in pyformlang syntax: a `head -> body | body` production per line,
+/// symbols starting with an uppercase letter are variables, `epsilon` and `$` stand for the empty word.
+/// `"VAR:x"` and `"TER:x"` make `x` a variable or a terminal whatever it starts with.
+#[derive(Debug)]
+struct Grammar {
+    variables: BTreeSet<String>,
+    productions: BTreeSet<Production>,
+}
+
+impl Grammar {
+    fn parse(text: &str) -> Result<Grammar> {
+        let mut variables = BTreeSet::new();
+        let mut productions = BTreeSet::new();
+        for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+            let arrow = line
+                .find("->")
+                .ok_or_else(|| Error::msg(format!("production without -> in {}", line)))?;
+            let head = special(line[..arrow].trim()).map_or(line[..arrow].trim(), |(_, s)| s);
+            variables.insert(head.to_string());
+
+            for alternative in line[arrow + 2..].split('|') {
+                let mut body = Vec::new();
+                for component in alternative.split_whitespace() {
+                    let (kind, name) = special(component).unwrap_or(("", component));
+                    let variable = kind == "VAR"
+                        || kind != "TER" && name.starts_with(|c: char| c.is_ascii_uppercase());
+                    if variable {
+                        variables.insert(name.to_string());
+                        body.push(Symbol::Variable(name.to_string()));
+                    } else if kind == "TER" || !EPSILON_SYMBOLS.contains(&name) {
+                        body.push(Symbol::Terminal(name.to_string()));
+                    }
+                }
+                productions.insert((head.to_string(), body));
+            }
+        }
+        Ok(Grammar {
+            variables,
+            productions,
+        })
+    }
+
+    /// Lifts terminals out of long bodies and splits those into pairs, then removes empty bodies,
+    /// unit productions and the variables no word of the start symbol goes through.
+    fn to_normal_form(&self, start: &str) -> NormalForm {
+        let mut productions = BTreeSet::new();
+        let mut split = 0;
+        for (head, body) in &self.productions {
+            if body.len() < 2 {
+                productions.insert((head.clone(), body.clone()));
+                continue;
+            }
+            let mut body: Vec<Symbol> = body
+                .iter()
+                .map(|symbol| match symbol {
+                    Symbol::Terminal(t) => {
+                        let variable = format!("{}#CNF#", t);
+                        productions.insert((variable.clone(), vec![symbol.clone()]));
+                        Symbol::Variable(variable)
+                    }
+                    variable => variable.clone(),
+                })
+                .collect();
+            let mut head = head.clone();
+            while body.len() > 2 {
+                split += 1;
+                let rest = format!("C#CNF#{}", split);
+                productions.insert((head, vec![body.remove(0), Symbol::Variable(rest.clone())]));
+                head = rest;
+            }
+            productions.insert((head, body));
+        }
+
+        let nullable = nullable(&productions);
+        let produces_epsilon = nullable.contains(start);
+        let mut without_epsilon = BTreeSet::new();
+        for (head, body) in &productions {
+            let optional = |symbol: &Symbol| match symbol {
+                Symbol::Variable(v) => nullable.contains(v),
+                Symbol::Terminal(_) => false,
+            };
+            for omitted in 0..1 << body.len() {
+                let kept: Vec<Symbol> = body
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, symbol)| omitted & 1 << i == 0 || !optional(symbol))
+                    .map(|(_, symbol)| symbol.clone())
+                    .collect();
+                if !kept.is_empty() {
+                    without_epsilon.insert((head.clone(), kept));
+                }
+            }
+        }
+
+        let mut units: BTreeMap<&String, BTreeSet<&String>> = BTreeMap::new();
+        for (head, body) in &without_epsilon {
+            units.entry(head).or_default().insert(head);
+            if let [Symbol::Variable(to)] = body.as_slice() {
+                units.entry(head).or_default().insert(to);
+            }
+        }
+        let mut changed = true;
+        while changed {
+            changed = false;
+            let heads: Vec<&String> = units.keys().cloned().collect();
+            for head in heads {
+                let reached: BTreeSet<&String> = units[head]
+                    .iter()
+                    .flat_map(|to| units.get(to).into_iter().flatten().cloned())
+                    .collect();
+                let set = units.get_mut(head).unwrap();
+                let size = set.len();
+                set.extend(reached);
+                changed |= set.len() != size;
+            }
+        }
+        let mut normal = BTreeSet::new();
+        for (head, tos) in &units {
+            for (to, body) in &without_epsilon {
+                let unit = matches!(body.as_slice(), [Symbol::Variable(_)]);
+                if !unit && tos.contains(to) {
+                    normal.insert(((*head).clone(), body.clone()));
+                }
+            }
+        }
+
+        let productions = useful(normal, start)
+            .into_iter()
+            .map(|(head, body)| {
+                let names = body
+                    .into_iter()
+                    .map(|symbol| match symbol {
+                        Symbol::Variable(s) | Symbol::Terminal(s) => s,
+                    })
+                    .collect();
+                (head, names)
+            })
+            .collect();
+        (
+            start.to_string(),
+            self.variables.iter().cloned().collect(),
+            productions,
+            produces_epsilon,
+        )
+    }
+}
+
+/// `"VAR:x"` or `"TER:x"` as the kind and `x`.
+fn special(text: &str) -> Option<(&str, &str)> {
+    let quoted = text.strip_prefix('"')?.strip_suffix('"')?;
+    ["VAR", "TER"].iter().find_map(|kind| {
+        let name = quoted.strip_prefix(kind)?.strip_prefix(':')?;
+        (!name.is_empty()).then_some((*kind, name))
+    })
+}
+
+fn nullable(productions: &BTreeSet<Production>) -> BTreeSet<String> {
+    let mut nullable = BTreeSet::new();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (head, body) in productions {
+            let empty = body.iter().all(|symbol| match symbol {
+                Symbol::Variable(v) => nullable.contains(v),
+                Symbol::Terminal(_) => false,
+            });
+            if empty && !nullable.contains(head) {
+                nullable.insert(head.clone());
+                changed = true;
+            }
+        }
+    }
+    nullable
+}
+
+/// Productions of the variables reachable from `start` that produce some word.
+fn useful(productions: BTreeSet<Production>, start: &str) -> BTreeSet<Production> {
+    let mut generating = BTreeSet::new();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (head, body) in &productions {
+            let generates = body.iter().all(|symbol| match symbol {
+                Symbol::Variable(v) => generating.contains(v),
+                Symbol::Terminal(_) => true,
+            });
+            if generates && !generating.contains(head) {
+                generating.insert(head.clone());
+                changed = true;
+            }
+        }
+    }
+    let productions: BTreeSet<Production> = productions
+        .into_iter()
+        .filter(|(head, body)| {
+            generating.contains(head)
+                && body.iter().all(|symbol| match symbol {
+                    Symbol::Variable(v) => generating.contains(v),
+                    Symbol::Terminal(_) => true,
+                })
+        })
+        .collect();
+
+    let mut reachable: BTreeSet<&str> = BTreeSet::new();
+    let mut stack = vec![start];
+    while let Some(variable) = stack.pop() {
+        if !reachable.insert(variable) {
+            continue;
+        }
+        for (_, body) in productions.iter().filter(|(head, _)| head == variable) {
+            stack.extend(body.iter().filter_map(|symbol| match symbol {
+                Symbol::Variable(v) => Some(v.as_str()),
+                Symbol::Terminal(_) => None,
+            }));
+        }
+    }
+    let reachable: BTreeSet<String> = reachable.into_iter().map(String::from).collect();
+    productions
+        .into_iter()
+        .filter(|(head, _)| reachable.contains(head))
+        .collect()
+}
+
+#[cfg(not(feature = "pyformlang"))]
+pub fn read_cfg_in_cnf(text: &str) -> Result<NormalForm> {
+    Ok(Grammar::parse(text)?.to_normal_form("S"))
+}
+
+#[cfg(feature = "pyformlang")]
+pub fn read_cfg_in_cnf(text: &str) -> Result<NormalForm> {
+    use pyo3::types::PyModule;
+    use pyo3::Python;
+    use std::str::from_utf8;
+
+    Python::with_gil(|py| -> Result<NormalForm> {
+        let module = PyModule::from_code(
+            py,
+            from_utf8(include_bytes!("py/read_cfg_in_cnf.py"))?,
+            "a.compute.py",
+            "a",
+        )?;
+        Ok(module.call1("parse_cfg", (text,))?.extract()?)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use anyhow::Result;
+
+    use super::*;
+
+    type Productions = HashSet<(String, Vec<String>)>;
+
+    fn normal_form(text: &str) -> Result<(Productions, bool)> {
+        let (start, _, productions, produces_epsilon) = Grammar::parse(text)?.to_normal_form("S");
+        assert_eq!("S", start);
+        for (_, body) in &productions {
+            assert!(body.len() == 1 || body.len() == 2);
+        }
+        Ok((productions.into_iter().collect(), produces_epsilon))
+    }
+
+    fn production(head: &str, body: &[&str]) -> (String, Vec<String>) {
+        (head.to_string(), body.iter().map(|s| s.to_string()).collect())
+    }
+
+    #[test]
+    fn parse() -> Result<()> {
+        let grammar = Grammar::parse("S -> a S | \"VAR:b\" $\n\nB -> \"TER:C\" epsilon\n")?;
+        let variables: Vec<&str> = grammar.variables.iter().map(String::as_str).collect();
+        assert_eq!(vec!["B", "S", "b"], variables);
+        let terminal = |s: &str| Symbol::Terminal(s.to_string());
+        let variable = |s: &str| Symbol::Variable(s.to_string());
+        let expected: BTreeSet<Production> = vec![
+            ("S".to_string(), vec![terminal("a"), variable("S")]),
+            ("S".to_string(), vec![variable("b")]),
+            ("B".to_string(), vec![terminal("C")]),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(expected, grammar.productions);
+        assert!(Grammar::parse("S a S").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn epsilon_and_units() -> Result<()> {
+        let (productions, produces_epsilon) = normal_form("S -> a S b | A\nA -> c | $")?;
+        assert!(produces_epsilon);
+        let expected: HashSet<_> = vec![
+            production("S", &["a#CNF#", "C#CNF#1"]),
+            production("S", &["c"]),
+            production("C#CNF#1", &["S", "b#CNF#"]),
+            production("C#CNF#1", &["b"]),
+            production("a#CNF#", &["a"]),
+            production("b#CNF#", &["b"]),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(expected, productions);
+        Ok(())
+    }
+
+    #[test]
+    fn useless() -> Result<()> {
+        let (productions, produces_epsilon) = normal_form("S -> a | B\nB -> B b\nC -> c")?;
+        assert!(!produces_epsilon);
+        assert_eq!(vec![production("S", &["a"])].into_iter().collect::<Productions>(), productions);
+
+        let (productions, produces_epsilon) = normal_form("A -> a")?;
+        assert!(!produces_epsilon);
+        assert!(productions.is_empty());
+        Ok(())
+    }
+}
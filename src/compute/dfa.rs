@@ -2,13 +2,12 @@ use std::collections::HashSet;
 use std::fs::File;
 use std::io::{BufRead, BufReader, Read};
 use std::path::Path;
-use std::str::from_utf8;
 
 use anyhow::{Error, Result};
-use pyo3::Python;
-use pyo3::types::PyModule;
 
-use super::graph::{Edge, Ends, ExtractPairs, Graph};
+use super::graph::{Ends, ExtractPairs, Graph};
+use super::limits::{ExecutionLimits, LimitExceeded};
+use super::regex::regex_to_edges;
 
 #[derive(Clone)]
 pub struct Dfa {
@@ -38,18 +37,7 @@ impl Dfa {
     }
 
     pub fn from_regex(regex: &str) -> Result<Dfa> {
-        let (initial, finals, edges) =
-            Python::with_gil(|py| -> Result<(usize, Vec<usize>, Vec<Edge>)> {
-                let module = PyModule::from_code(
-                    py,
-                    from_utf8(include_bytes!("py/regex_to_edges.py"))?,
-                    "a.compute.py",
-                    "a",
-                )?;
-                let py_res: (usize, Vec<usize>, Vec<(usize, usize, String)>) =
-                    module.call1("regex_to_edges", (regex,))?.extract()?;
-                Ok(py_res)
-            })?;
+        let (initial, finals, edges) = regex_to_edges(regex)?;
 
         Ok(Dfa {
             graph: Graph::build(edges.as_slice()),
@@ -58,7 +46,8 @@ impl Dfa {
         })
     }
 
-    pub fn intersection(&self, b: &Dfa) -> Dfa {
+    pub fn intersection(&self, b: &Dfa, limits: &ExecutionLimits) -> Result<Dfa, LimitExceeded> {
+        limits.check("kronecker product", self.graph.kronecker_nvals(&b.graph))?;
         let graph = self.graph.kronecker(&b.graph);
         let mut initials = HashSet::with_capacity(self.initials.len() * b.initials.len());
 
@@ -75,10 +64,19 @@ impl Dfa {
             }
         }
 
-        Dfa {
+        Ok(Dfa {
             graph,
             initials,
             finals,
+        })
+    }
+
+    /// The same automaton with every state `v` renamed to `rename(v)`, `rename` is a permutation.
+    pub(crate) fn renumbered<F: Fn(usize) -> usize>(&self, rename: F) -> Dfa {
+        Dfa {
+            graph: self.graph.renumbered(&rename),
+            initials: self.initials.iter().map(|v| rename(*v)).collect(),
+            finals: self.finals.iter().map(|v| rename(*v)).collect(),
         }
     }
 
@@ -88,8 +86,8 @@ impl Dfa {
     }
 
     fn walk(&self, position: usize, word: &[&str]) -> bool {
-        if word.len() == 0 && self.finals.contains(&position) {
-            return true;
+        if word.is_empty() {
+            return self.finals.contains(&position);
         }
 
         if let Some(matrix) = self.graph.get(word[0]) {
@@ -104,18 +102,26 @@ impl Dfa {
 }
 
 impl Graph {
-    pub fn rpq(&self, request: &Dfa) -> HashSet<Ends> {
+    pub fn rpq(
+        &self,
+        request: &Dfa,
+        limits: &ExecutionLimits,
+    ) -> Result<HashSet<Ends>, LimitExceeded> {
+        limits.check("kronecker product", self.kronecker_nvals(&request.graph))?;
         let g = self.kronecker(&request.graph);
         let size = request.graph.size;
-        g.reachable_pairs_filter(|(from, to)| {
-            let ref request_from = from % size;
-            let ref request_to = to % size;
-            if request.initials.contains(request_from) && request.finals.contains(request_to) {
-                Some((from / size, to / size))
-            } else {
-                None
-            }
-        })
+        g.reachable_pairs_filter(
+            |(from, to)| {
+                let ref request_from = from % size;
+                let ref request_to = to % size;
+                if request.initials.contains(request_from) && request.finals.contains(request_to) {
+                    Some((from / size, to / size))
+                } else {
+                    None
+                }
+            },
+            limits,
+        )
     }
 }
 
@@ -129,7 +135,7 @@ mod tests {
     use crate::compute::graph::{Ends, Graph};
 
     fn assert_reachable(a: &Graph, b: &Dfa, pairs: &[Ends]) {
-        let res = a.rpq(b);
+        let res = a.rpq(b, &ExecutionLimits::default()).unwrap();
         let actual: HashSet<&Ends> = res.iter().collect();
         let expected: HashSet<&Ends> = pairs.iter().collect();
 
@@ -183,7 +189,7 @@ mod tests {
     fn test_intersection() -> Result<()> {
         let ab = Dfa::from_regex("(a|b)*")?;
         let bc = Dfa::from_regex("(c|b)*")?;
-        let bi = ab.intersection(&bc);
+        let bi = ab.intersection(&bc, &ExecutionLimits::default())?;
 
         assert!(bi.accepts(&["b", "b"]));
         assert!(!bi.accepts(&["a", "a"]));
@@ -1,8 +1,8 @@
-use std::borrow::Borrow;
+use std::borrow::{Borrow, Cow};
 use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::hash::Hash;
-use std::io::{BufRead, BufReader};
+use std::io::{BufRead, BufReader, Write};
 use std::path::Path;
 use std::rc::Rc;
 use std::str::FromStr;
@@ -11,10 +11,39 @@ use anyhow::{Error, Result};
 
 use graphblas::*;
 
+use crate::compute::limits::{ExecutionLimits, LimitExceeded};
+use crate::compute::string::{literal, read_literal};
+
 pub type Ends = (usize, usize);
 pub type Edge = (usize, usize, String);
 pub(crate) type BooleanMatrix = BaseTypeMatrix<bool>;
 
+const INVERSE_PREFIX: char = '^';
+/// Prefix of the transitions that query automata use for their own needs, such as nonterminals.
+const RESERVED_PREFIX: char = '<';
+const ESCAPE: char = '\\';
+
+/// Key of the matrix of `label` in query automata and in the graphs they are applied to:
+/// the label itself, escaped when it starts with a prefix of the other keys.
+/// So no label is the key of an inverse label or of a reserved transition.
+pub fn label_key(label: &str) -> Cow<'_, str> {
+    if label.starts_with(&[INVERSE_PREFIX, RESERVED_PREFIX, ESCAPE][..]) {
+        Cow::Owned(format!("{}{}", ESCAPE, label))
+    } else {
+        Cow::Borrowed(label)
+    }
+}
+
+/// Key of the edges of `label` traversed backwards.
+pub fn inverse(label: &str) -> String {
+    format!("{}{}", INVERSE_PREFIX, label)
+}
+
+/// The label an inverse key is made of, `None` for a key that is not inverse.
+pub fn inverted(key: &str) -> Option<&str> {
+    key.strip_prefix(INVERSE_PREFIX)
+}
+
 #[derive(Clone)]
 pub struct Graph {
     pub(crate) matrices: HashMap<String, BooleanMatrix>,
@@ -22,6 +51,39 @@ pub struct Graph {
     pub new_matrix: Rc<dyn Fn() -> BooleanMatrix>,
 }
 
+/// Parses a `from label to` line, the label is either a word or a quoted string literal.
+/// Anything after `to` is ignored.
+fn parse_edge(line: &str) -> Result<Edge> {
+    let invalid = || Error::msg(format!("invalid format: {}", line));
+    let mut split = line.splitn(2, ' ');
+    let from = usize::from_str(split.next().ok_or_else(invalid)?)?;
+    let rest = split.next().ok_or_else(invalid)?;
+    let (label, rest) = if rest.starts_with('"') {
+        read_literal(rest).ok_or_else(invalid)?
+    } else {
+        let mut split = rest.splitn(2, ' ');
+        let label = split.next().ok_or_else(invalid)?;
+        (label.to_string(), split.next().unwrap_or(""))
+    };
+    let to = rest.trim_start().split(' ').next().ok_or_else(invalid)?;
+    Ok((from, usize::from_str(to)?, label))
+}
+
+/// Parses a `from,label,to` line, the label may be quoted as in CSV with `""` for a quote.
+fn parse_csv_edge(line: &str) -> Result<Edge> {
+    let invalid = || Error::msg(format!("invalid format: {}", line));
+    let mut split = line.splitn(2, ',');
+    let from = usize::from_str(split.next().ok_or_else(invalid)?.trim())?;
+    let rest = split.next().ok_or_else(invalid)?;
+    let comma = rest.rfind(',').ok_or_else(invalid)?;
+    let label = rest[..comma].trim();
+    let label = match label.strip_prefix('"').and_then(|l| l.strip_suffix('"')) {
+        Some(quoted) => quoted.replace("\"\"", "\""),
+        None => label.to_string(),
+    };
+    Ok((from, usize::from_str(rest[comma + 1..].trim())?, label))
+}
+
 impl Graph {
     pub(crate) fn with_size(size: usize) -> Graph {
         Graph {
@@ -37,11 +99,25 @@ impl Graph {
         Graph::from_lines(reader.lines().map(|f| Ok(f?)))
     }
 
+    /// Reads a graph of `from,label,to` lines.
+    pub fn read_csv_from<P: AsRef<Path>>(path: P) -> Result<Graph> {
+        let file = File::open(path)?;
+        let reader = BufReader::new(file);
+        Graph::parse_lines(reader.lines().map(|f| Ok(f?)), parse_csv_edge)
+    }
+
     pub fn from_text(text: &str) -> Result<Graph> {
         Graph::from_lines(text.to_string().split("\n").map(str::to_string).map(Ok))
     }
 
     pub fn from_lines<I: Iterator<Item = Result<String>>>(lines: I) -> Result<Graph> {
+        Graph::parse_lines(lines, parse_edge)
+    }
+
+    fn parse_lines<I: Iterator<Item = Result<String>>>(
+        lines: I,
+        parse: fn(&str) -> Result<Edge>,
+    ) -> Result<Graph> {
         let edges = lines
             .filter(|line| {
                 if let Ok(line) = line {
@@ -51,24 +127,37 @@ impl Graph {
                 }
             })
             .map(|line| match line {
-                Ok(line) => {
-                    let split: Vec<&str> = line.split(" ").collect();
-                    if split.len() < 3 {
-                        Err(Error::msg("invalid format"))?
-                    } else {
-                        let from = usize::from_str(split[0])?;
-                        let to = usize::from_str(split[2])?;
-
-                        let label = split[1].to_string();
-                        Ok((from, to, label))
-                    }
-                }
+                Ok(line) => parse(&line),
                 Err(e) => Err(e),
             });
 
         Graph::from_edges(edges)
     }
 
+    /// Writes the edges in the `from label to` format read by `from_lines`, sorted by label.
+    /// Labels that cannot be written as they are, such as ones with spaces, are quoted.
+    /// Vertices after the last one with an edge are not written, the graph read back has fewer of them.
+    pub fn write_to<W: Write>(&self, output: &mut W) -> Result<()> {
+        let mut labels: Vec<&String> = self.matrices.keys().collect();
+        labels.sort();
+        for label in labels {
+            let written_label = if label.is_empty()
+                || label.contains(char::is_whitespace)
+                || label.starts_with('"')
+            {
+                literal(label)
+            } else {
+                label.clone()
+            };
+            let mut pairs = self.matrices[label].extract_pairs();
+            pairs.sort();
+            for (from, to) in pairs {
+                writeln!(output, "{} {} {}", from, written_label, to)?;
+            }
+        }
+        Ok(())
+    }
+
     pub fn from_edges<I: Iterator<Item = Result<Edge>>>(edges: I) -> Result<Graph> {
         let mut size: usize = 0;
         let mut label_paths = HashMap::<String, (Vec<u64>, Vec<u64>)>::new();
@@ -118,6 +207,18 @@ impl Graph {
         Graph::from_edges(edges.iter().cloned().map(Ok)).unwrap()
     }
 
+    /// Labels that have edges, sorted.
+    pub fn alphabet(&self) -> Vec<String> {
+        let mut labels: Vec<String> = self
+            .matrices
+            .iter()
+            .filter(|(_, matrix)| matrix.nvals() > 0)
+            .map(|(label, _)| label.clone())
+            .collect();
+        labels.sort();
+        labels
+    }
+
     pub fn get_stats(&self) -> HashMap<String, usize> {
         self.matrices
             .iter()
@@ -125,6 +226,158 @@ impl Graph {
             .collect()
     }
 
+    /// The same edges over `size` vertices, `size` is expected to be not less than `self.size`.
+    pub(crate) fn resized(&self, size: usize) -> Graph {
+        if size == self.size {
+            return self.clone();
+        }
+        let mut graph = Graph::with_size(size);
+        for (label, matrix) in &self.matrices {
+            let (froms, tos, values) = matrix.extract_tuples();
+            graph.get_mut(label.clone()).build(
+                &froms,
+                &tos,
+                &values,
+                BinaryOp::<bool, bool, bool>::first(),
+            );
+        }
+        graph
+    }
+
+    /// The same edges with every vertex `v` renamed to `rename(v)`, `rename` is a permutation.
+    pub(crate) fn renumbered<F: Fn(usize) -> usize>(&self, rename: F) -> Graph {
+        let mut graph = Graph::with_size(self.size);
+        for (label, matrix) in &self.matrices {
+            let (froms, tos, values) = matrix.extract_tuples();
+            let renamed = |vertices: Vec<u64>| -> Vec<u64> {
+                vertices
+                    .into_iter()
+                    .map(|v| rename(v as usize) as u64)
+                    .collect()
+            };
+            graph.get_mut(label.clone()).build(
+                renamed(froms),
+                renamed(tos),
+                &values,
+                BinaryOp::<bool, bool, bool>::first(),
+            );
+        }
+        graph
+    }
+
+    /// Edges of both graphs, vertices with the same ids are the same.
+    pub fn union(&self, other: &Graph) -> Graph {
+        let size = self.size.max(other.size);
+        let mut graph = self.resized(size);
+        for (label, matrix) in &other.resized(size).matrices {
+            graph.get_mut(label.clone()).accumulate_apply(
+                BinaryOp::<bool, bool, bool>::lor(),
+                UnaryOp::<bool, bool>::identity(),
+                matrix,
+            );
+        }
+        graph
+    }
+
+    /// Edges of `self` that `other` does not have with the same label.
+    pub fn difference(&self, other: &Graph) -> Graph {
+        let mut graph = Graph::with_size(self.size);
+        let other = other.resized(self.size.max(other.size));
+        for (label, matrix) in &self.matrices {
+            let result = graph.get_mut(label.clone());
+            match other.get(label) {
+                Some(mask) if mask.nrows() == matrix.nrows() => result.assign_apply_masked(
+                    mask,
+                    UnaryOp::<bool, bool>::identity(),
+                    matrix,
+                    Descriptor::replace_complement_mask(),
+                ),
+                Some(mask) => {
+                    let (froms, tos, _) = matrix.extract_tuples();
+                    let kept: Vec<(u64, u64)> = froms
+                        .into_iter()
+                        .zip(tos)
+                        .filter(|(from, to)| mask.get(*from, *to).is_none())
+                        .collect();
+                    let (froms, tos): (Vec<u64>, Vec<u64>) = kept.into_iter().unzip();
+                    result.build(
+                        &froms,
+                        &tos,
+                        vec![true; froms.len()],
+                        BinaryOp::<bool, bool, bool>::first(),
+                    );
+                }
+                None => result.assign_apply(UnaryOp::<bool, bool>::identity(), matrix),
+            }
+        }
+        graph
+    }
+
+    /// The graph with every edge turned backwards.
+    pub fn reversed(&self) -> Graph {
+        let mut graph = Graph::with_size(self.size);
+        for (label, matrix) in &self.matrices {
+            graph.get_mut(label.clone()).assign_transpose(matrix);
+        }
+        graph
+    }
+
+    /// The graph with edges of the given labels only.
+    pub fn restricted<S: AsRef<str>>(&self, labels: &[S]) -> Graph {
+        let mut graph = Graph::with_size(self.size);
+        for label in labels {
+            if let Some(matrix) = self.get(label.as_ref()) {
+                graph
+                    .matrices
+                    .insert(label.as_ref().to_string(), matrix.clone());
+            }
+        }
+        graph
+    }
+
+    /// The graph with its matrices under the keys of query automata (see `label_key`)
+    /// and the transposed matrices of the inverse ones among `keys` added,
+    /// the graph itself if nothing is renamed or added.
+    pub fn with_inverses<'a, I: IntoIterator<Item = &'a String>>(
+        &self,
+        keys: I,
+    ) -> Cow<'_, Graph> {
+        let mut graph = Cow::Borrowed(self);
+        let renamed: Vec<(&String, String)> = self
+            .matrices
+            .keys()
+            .filter_map(|label| match label_key(label) {
+                Cow::Owned(key) => Some((label, key)),
+                Cow::Borrowed(_) => None,
+            })
+            .collect();
+        if !renamed.is_empty() {
+            let matrices = &mut graph.to_mut().matrices;
+            let moved: Vec<(String, BooleanMatrix)> = renamed
+                .into_iter()
+                .map(|(label, key)| (key, matrices.remove(label).unwrap()))
+                .collect();
+            matrices.extend(moved);
+        }
+        for key in keys {
+            if let Some(matrix) = inverted(key).and_then(|label| self.get(label)) {
+                graph
+                    .to_mut()
+                    .get_mut(key.clone())
+                    .assign_transpose(matrix);
+            }
+        }
+        graph
+    }
+
+    /// Entries of `self.kronecker(b)`, computed without it.
+    pub(crate) fn kronecker_nvals(&self, b: &Graph) -> u64 {
+        self.matrices
+            .iter()
+            .filter_map(|(label, m)| b.matrices.get(label).map(|om| m.nvals() * om.nvals()))
+            .sum()
+    }
+
     pub fn kronecker(&self, b: &Graph) -> Graph {
         let mut graph = Graph::with_size(self.size * b.size);
         self.matrices
@@ -137,16 +390,34 @@ impl Graph {
         graph
     }
 
-    pub fn reachable_pairs(&self) -> Vec<Ends> {
+    pub fn reachable_pairs(&self, limits: &ExecutionLimits) -> Result<Vec<Ends>, LimitExceeded> {
         let mut closure = self.adjacency_matrix();
-        Graph::close_with_squaring(&mut closure);
-        closure.extract_pairs()
+        Graph::close_with_squaring(&mut closure, limits)?;
+        Ok(closure.extract_pairs())
     }
 
-    pub fn reachable_pairs_filter<F: Fn(Ends) -> Option<Ends>>(&self, filter: F) -> HashSet<Ends> {
+    pub fn reachable_pairs_filter<F: Fn(Ends) -> Option<Ends>>(
+        &self,
+        filter: F,
+        limits: &ExecutionLimits,
+    ) -> Result<HashSet<Ends>, LimitExceeded> {
         let mut closure = self.adjacency_matrix();
-        Graph::close_with_squaring(&mut closure);
-        closure.extract_pairs_filter(filter)
+        Graph::close_with_squaring(&mut closure, limits)?;
+        Ok(closure.extract_pairs_filter(filter))
+    }
+
+    /// Matrix with an entry for each of the pairs.
+    pub(crate) fn pairs_matrix(&self, pairs: &[Ends]) -> BooleanMatrix {
+        let froms: Vec<u64> = pairs.iter().map(|(from, _)| *from as u64).collect();
+        let tos: Vec<u64> = pairs.iter().map(|(_, to)| *to as u64).collect();
+        let mut m = self.new_matrix.call(());
+        m.build(
+            &froms,
+            &tos,
+            vec![true; pairs.len()],
+            BinaryOp::<bool, bool, bool>::first(),
+        );
+        m
     }
 
     pub(crate) fn adjacency_matrix(&self) -> BooleanMatrix {
@@ -161,10 +432,15 @@ impl Graph {
         m
     }
 
-    pub(crate) fn close_with_squaring(m: &mut BooleanMatrix) {
+    pub(crate) fn close_with_squaring(
+        m: &mut BooleanMatrix,
+        limits: &ExecutionLimits,
+    ) -> Result<(), LimitExceeded> {
+        let mut progress = limits.start("closure by squaring");
         let mut prev = 0;
         let mut square = Matrix::<bool>::new(m.nrows(), m.ncols());
         while prev != m.nvals() {
+            progress.iteration(m.nvals())?;
             prev = m.nvals();
             square.clear();
             square.accumulate_mxm(
@@ -179,13 +455,19 @@ impl Graph {
                 &square,
             );
         }
+        Ok(())
     }
 
-    pub(crate) fn close_with_adjacency_matrix(m: &mut BooleanMatrix) {
+    pub(crate) fn close_with_adjacency_matrix(
+        m: &mut BooleanMatrix,
+        limits: &ExecutionLimits,
+    ) -> Result<(), LimitExceeded> {
+        let mut progress = limits.start("closure by adjacency matrix products");
         let adj = m.clone();
         let mut prev = 0;
         let mut production = Matrix::<bool>::new(m.nrows(), m.ncols());
         while prev != m.nvals() {
+            progress.iteration(m.nvals())?;
             prev = m.nvals();
             production.clear();
             production.accumulate_mxm(
@@ -200,9 +482,39 @@ impl Graph {
                 &production,
             );
         }
+        Ok(())
     }
 }
 
+pub(crate) trait Degrees {
+    /// Numbers of entries of the nonempty rows, by row.
+    fn out_degrees(&self) -> Vec<(usize, usize)>;
+
+    /// Numbers of entries of the nonempty columns, by column.
+    fn in_degrees(&self) -> Vec<(usize, usize)>;
+}
+
+impl Degrees for BooleanMatrix {
+    fn out_degrees(&self) -> Vec<(usize, usize)> {
+        reduce_to_degrees(self, Descriptor::default())
+    }
+
+    fn in_degrees(&self) -> Vec<(usize, usize)> {
+        reduce_to_degrees(self, Descriptor::transpose_first())
+    }
+}
+
+fn reduce_to_degrees<D: Descriptor>(matrix: &BooleanMatrix, desc: &D) -> Vec<(usize, usize)> {
+    let mut degrees = Vector::<u32>::new(matrix.nrows());
+    degrees.assign_reduce(Monoid::<u32>::plus(), matrix, desc);
+    let (vertices, counts) = degrees.extract_tuples();
+    vertices
+        .into_iter()
+        .map(|v| v as usize)
+        .zip(counts.into_iter().map(|c| c as usize))
+        .collect()
+}
+
 pub(crate) trait ExtractPairs {
     fn extract_pairs(&self) -> Vec<Ends>;
 
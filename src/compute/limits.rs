@@ -0,0 +1,250 @@
+use std::fmt::{Display, Formatter};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Flag shared with the computations checking it, set from another thread to stop them.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> CancellationToken {
+        CancellationToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst)
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// Bounds of the fixpoint loops of path queries, a loop checks them before each iteration.
+/// The default has no bounds.
+#[derive(Debug, Clone, Default)]
+pub struct ExecutionLimits {
+    pub deadline: Option<Instant>,
+    /// Entries the largest matrix of a loop may have.
+    pub max_nvals: Option<u64>,
+    /// Iterations of a single loop. Hellings' algorithm counts each pair it takes
+    /// from its worklist as an iteration, so it needs about as many as it finds pairs.
+    pub max_iterations: Option<usize>,
+    pub cancellation: Option<CancellationToken>,
+}
+
+impl ExecutionLimits {
+    /// Checks the limits before a step that is not a loop, such as building a matrix of `nvals` entries.
+    /// Unlike an iteration, it is not bounded by `max_iterations`.
+    pub fn check(&self, computation: &'static str, nvals: u64) -> Result<(), LimitExceeded> {
+        self.start(computation).step(nvals, false)
+    }
+
+    /// Starts counting the iterations of the loop named `computation`.
+    pub fn start(&self, computation: &'static str) -> Progress<'_> {
+        Progress {
+            limits: self,
+            computation,
+            started: Instant::now(),
+            iterations: 0,
+        }
+    }
+}
+
+/// Iterations of a loop so far.
+pub struct Progress<'a> {
+    limits: &'a ExecutionLimits,
+    computation: &'static str,
+    started: Instant,
+    iterations: usize,
+}
+
+impl Progress<'_> {
+    /// Checks the limits before an iteration, `nvals` is the number of entries
+    /// of the largest matrix of the loop.
+    pub fn iteration(&mut self, nvals: u64) -> Result<(), LimitExceeded> {
+        self.step(nvals, true)
+    }
+
+    fn step(&mut self, nvals: u64, counted: bool) -> Result<(), LimitExceeded> {
+        let limits = self.limits;
+        let cancelled = limits
+            .cancellation
+            .as_ref()
+            .is_some_and(CancellationToken::is_cancelled);
+        let max_iterations = limits.max_iterations.filter(|_| counted);
+        let limit = if cancelled {
+            Limit::Cancelled
+        } else if limits.deadline.is_some_and(|d| Instant::now() >= d) {
+            Limit::Deadline
+        } else if let Some(max) = limits.max_nvals.filter(|max| nvals > *max) {
+            Limit::Nvals(max)
+        } else if let Some(max) = max_iterations.filter(|max| self.iterations >= *max) {
+            Limit::Iterations(max)
+        } else {
+            self.iterations += 1;
+            return Ok(());
+        };
+        Err(LimitExceeded {
+            limit,
+            computation: self.computation,
+            iterations: self.iterations,
+            nvals,
+            elapsed: self.started.elapsed(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    Deadline,
+    Nvals(u64),
+    Iterations(usize),
+    Cancelled,
+}
+
+/// A loop stopped by its limits, with the progress it made.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LimitExceeded {
+    pub limit: Limit,
+    /// Name of the stopped loop.
+    pub computation: &'static str,
+    /// Iterations completed before it was stopped.
+    pub iterations: usize,
+    /// Entries of its largest matrix when it was stopped.
+    pub nvals: u64,
+    pub elapsed: Duration,
+}
+
+impl Display for Limit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Limit::Deadline => write!(f, "the deadline has passed"),
+            Limit::Nvals(max) => write!(f, "a matrix has more than {} entries", max),
+            Limit::Iterations(max) => write!(f, "more than {} iterations are needed", max),
+            Limit::Cancelled => write!(f, "cancelled"),
+        }
+    }
+}
+
+impl Display for LimitExceeded {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} stopped after {} iterations in {:.3}s with {} entries in the largest matrix: {}",
+            self.computation,
+            self.iterations,
+            self.elapsed.as_secs_f64(),
+            self.nvals,
+            self.limit
+        )
+    }
+}
+
+impl std::error::Error for LimitExceeded {}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use anyhow::Result;
+
+    use crate::compute::dfa::Dfa;
+    use crate::compute::graph::Graph;
+
+    use super::*;
+
+    /// Path 0 -> 1 -> ... -> n, its closure by squaring needs about log2(n) iterations.
+    fn path(n: usize) -> Graph {
+        let edges: Vec<_> = (0..n).map(|i| (i, i + 1, "a".to_string())).collect();
+        Graph::build(&edges)
+    }
+
+    #[test]
+    fn unlimited() -> Result<()> {
+        let pairs = path(16).reachable_pairs(&ExecutionLimits::default())?;
+        assert_eq!(16 * 17 / 2, pairs.len());
+        Ok(())
+    }
+
+    #[test]
+    fn iterations() {
+        let limits = ExecutionLimits {
+            max_iterations: Some(2),
+            ..ExecutionLimits::default()
+        };
+        let error = path(16).reachable_pairs(&limits).unwrap_err();
+        assert_eq!(Limit::Iterations(2), error.limit);
+        assert_eq!(2, error.iterations);
+        assert_eq!(16 + 15 + 14 + 13, error.nvals);
+        assert!(path(2).reachable_pairs(&limits).is_ok());
+    }
+
+    #[test]
+    fn nvals() {
+        let limits = ExecutionLimits {
+            max_nvals: Some(20),
+            ..ExecutionLimits::default()
+        };
+        let error = path(16).reachable_pairs(&limits).unwrap_err();
+        assert_eq!(Limit::Nvals(20), error.limit);
+        assert_eq!(1, error.iterations);
+    }
+
+    #[test]
+    fn kronecker_product() {
+        let limits = ExecutionLimits {
+            max_nvals: Some(20),
+            ..ExecutionLimits::default()
+        };
+        // `"a"+`, each of its 2 edges gives a copy of the 16 edges of the path
+        let request = Dfa {
+            graph: Graph::build(&[(0, 1, "a".to_string()), (1, 1, "a".to_string())]),
+            initials: [0].iter().cloned().collect(),
+            finals: [1].iter().cloned().collect(),
+        };
+        let error = path(16).rpq(&request, &limits).unwrap_err();
+        assert_eq!("kronecker product", error.computation);
+        assert_eq!(Limit::Nvals(20), error.limit);
+        assert_eq!(0, error.iterations);
+        assert_eq!(32, error.nvals);
+        assert!(path(4).rpq(&request, &limits).is_ok());
+
+        let path = Dfa {
+            graph: path(16),
+            initials: [0].iter().cloned().collect(),
+            finals: [16].iter().cloned().collect(),
+        };
+        let error = path.intersection(&request, &limits).err().unwrap();
+        assert_eq!("kronecker product", error.computation);
+        assert_eq!(Limit::Nvals(20), error.limit);
+
+        let limits = ExecutionLimits {
+            max_iterations: Some(0),
+            ..ExecutionLimits::default()
+        };
+        assert!(path.intersection(&request, &limits).is_ok());
+    }
+
+    #[test]
+    fn deadline_and_cancellation() {
+        let limits = ExecutionLimits {
+            deadline: Some(Instant::now()),
+            ..ExecutionLimits::default()
+        };
+        let error = path(4).reachable_pairs(&limits).unwrap_err();
+        assert_eq!(Limit::Deadline, error.limit);
+        assert_eq!(0, error.iterations);
+
+        let token = CancellationToken::new();
+        let limits = ExecutionLimits {
+            cancellation: Some(token.clone()),
+            ..ExecutionLimits::default()
+        };
+        assert!(path(4).reachable_pairs(&limits).is_ok());
+        token.cancel();
+        let error = path(4).reachable_pairs(&limits).unwrap_err();
+        assert_eq!(Limit::Cancelled, error.limit);
+    }
+}
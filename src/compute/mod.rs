@@ -1,4 +1,8 @@
 pub mod cfg;
+pub mod cnf;
 pub mod dfa;
 pub mod graph;
-pub mod rfa;
\ No newline at end of file
+pub mod limits;
+pub mod regex;
+pub mod rfa;
+pub mod string;
\ No newline at end of file
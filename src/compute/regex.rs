@@ -0,0 +1,438 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::iter::Peekable;
+use std::str::Chars;
+
+use anyhow::{Error, Result};
+
+use super::graph::Edge;
+
+/// Initial state, final states and labeled edges of a minimal deterministic automaton.
+pub type AutomatonEdges = (usize, Vec<usize>, Vec<Edge>);
+
+/// Regular expression in pyformlang syntax: `*` is the Kleene star, `|` and `+` are unions,
+/// `.` or whitespace is a concatenation, `epsilon` and `$` stand for the empty word.
+/// Any other run of characters is a single symbol.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Regex {
+    Epsilon,
+    Symbol(String),
+    Concat(Box<Regex>, Box<Regex>),
+    Union(Box<Regex>, Box<Regex>),
+    Star(Box<Regex>),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Token {
+    Open,
+    Close,
+    Star,
+    Union,
+    Concat,
+    Epsilon,
+    Symbol(String),
+}
+
+const SPECIALS: &[char] = &['(', ')', '*', '|', '+', '.', '$'];
+
+struct Lexer<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        while self.chars.peek()?.is_whitespace() {
+            self.chars.next();
+        }
+
+        let token = match self.chars.next()? {
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '*' => Token::Star,
+            '|' | '+' => Token::Union,
+            '.' => Token::Concat,
+            '$' => Token::Epsilon,
+            c => {
+                let mut symbol = c.to_string();
+                while let Some(&c) = self.chars.peek() {
+                    if c.is_whitespace() || SPECIALS.contains(&c) {
+                        break;
+                    }
+                    symbol.push(c);
+                    self.chars.next();
+                }
+                match symbol.as_str() {
+                    "epsilon" => Token::Epsilon,
+                    _ => Token::Symbol(symbol),
+                }
+            }
+        };
+        Some(token)
+    }
+}
+
+struct Parser<'a> {
+    tokens: Peekable<Lexer<'a>>,
+}
+
+impl<'a> Parser<'a> {
+    fn union(&mut self) -> Result<Regex> {
+        let mut regex = self.concat()?;
+        while let Some(Token::Union) = self.tokens.peek() {
+            self.tokens.next();
+            regex = Regex::Union(Box::new(regex), Box::new(self.concat()?));
+        }
+        Ok(regex)
+    }
+
+    fn concat(&mut self) -> Result<Regex> {
+        let mut regex = self.star()?;
+        loop {
+            match self.tokens.peek() {
+                Some(Token::Concat) => {
+                    self.tokens.next();
+                }
+                Some(Token::Open) | Some(Token::Epsilon) | Some(Token::Symbol(_)) => {}
+                _ => return Ok(regex),
+            }
+            regex = Regex::Concat(Box::new(regex), Box::new(self.star()?));
+        }
+    }
+
+    fn star(&mut self) -> Result<Regex> {
+        let mut regex = self.primary()?;
+        while let Some(Token::Star) = self.tokens.peek() {
+            self.tokens.next();
+            regex = Regex::Star(Box::new(regex));
+        }
+        Ok(regex)
+    }
+
+    fn primary(&mut self) -> Result<Regex> {
+        match self.tokens.next() {
+            Some(Token::Open) => {
+                if let Some(Token::Close) = self.tokens.peek() {
+                    self.tokens.next();
+                    return Ok(Regex::Epsilon);
+                }
+                let regex = self.union()?;
+                match self.tokens.next() {
+                    Some(Token::Close) => Ok(regex),
+                    _ => Err(Error::msg("unbalanced parentheses in regex")),
+                }
+            }
+            Some(Token::Epsilon) => Ok(Regex::Epsilon),
+            Some(Token::Symbol(symbol)) => Ok(Regex::Symbol(symbol)),
+            Some(token) => Err(Error::msg(format!("unexpected {:?} in regex", token))),
+            None => Err(Error::msg("unexpected end of regex")),
+        }
+    }
+}
+
+impl Regex {
+    pub fn parse(text: &str) -> Result<Regex> {
+        let mut parser = Parser {
+            tokens: Lexer {
+                chars: text.chars().peekable(),
+            }
+            .peekable(),
+        };
+
+        if parser.tokens.peek().is_none() {
+            return Ok(Regex::Epsilon);
+        }
+
+        let regex = parser.union()?;
+        match parser.tokens.next() {
+            None => Ok(regex),
+            Some(token) => Err(Error::msg(format!("unexpected {:?} in regex", token))),
+        }
+    }
+
+    pub fn to_minimal_dfa(&self) -> AutomatonEdges {
+        let mut nfa = EpsilonNfa::new();
+        let (initial, last) = nfa.thompson(self);
+        nfa.to_minimal_dfa(initial, &[last])
+    }
+}
+
+/// Nondeterministic automaton with epsilon transitions, labeled by `None`.
+pub(crate) struct EpsilonNfa {
+    transitions: Vec<Vec<(Option<String>, usize)>>,
+}
+
+impl EpsilonNfa {
+    pub(crate) fn new() -> EpsilonNfa {
+        EpsilonNfa {
+            transitions: Vec::new(),
+        }
+    }
+
+    pub(crate) fn add_state(&mut self) -> usize {
+        self.transitions.push(Vec::new());
+        self.transitions.len() - 1
+    }
+
+    pub(crate) fn size(&self) -> usize {
+        self.transitions.len()
+    }
+
+    pub(crate) fn add_edge(&mut self, from: usize, to: usize, label: Option<String>) {
+        self.transitions[from].push((label, to));
+    }
+
+    /// Builds a fragment recognizing `regex`, returning its entry and exit states.
+    pub(crate) fn thompson(&mut self, regex: &Regex) -> (usize, usize) {
+        match regex {
+            Regex::Epsilon => {
+                let state = self.add_state();
+                (state, state)
+            }
+            Regex::Symbol(symbol) => {
+                let (from, to) = (self.add_state(), self.add_state());
+                self.add_edge(from, to, Some(symbol.clone()));
+                (from, to)
+            }
+            Regex::Concat(r1, r2) => {
+                let (from1, to1) = self.thompson(r1);
+                let (from2, to2) = self.thompson(r2);
+                self.add_edge(to1, from2, None);
+                (from1, to2)
+            }
+            Regex::Union(r1, r2) => {
+                let (from, to) = (self.add_state(), self.add_state());
+                for r in &[r1, r2] {
+                    let (inner_from, inner_to) = self.thompson(r);
+                    self.add_edge(from, inner_from, None);
+                    self.add_edge(inner_to, to, None);
+                }
+                (from, to)
+            }
+            Regex::Star(r) => {
+                let state = self.add_state();
+                let (from, to) = self.thompson(r);
+                self.add_edge(state, from, None);
+                self.add_edge(to, state, None);
+                (state, state)
+            }
+        }
+    }
+
+    fn closure(&self, states: BTreeSet<usize>) -> BTreeSet<usize> {
+        let mut closure = states;
+        let mut queue: VecDeque<usize> = closure.iter().cloned().collect();
+        while let Some(state) = queue.pop_front() {
+            for (label, to) in &self.transitions[state] {
+                if label.is_none() && closure.insert(*to) {
+                    queue.push_back(*to);
+                }
+            }
+        }
+        closure
+    }
+
+    /// Subset construction followed by minimization. The result has no dead states
+    /// and its states are numbered in breadth-first order, so the initial state is `0`.
+    pub(crate) fn to_minimal_dfa(&self, initial: usize, finals: &[usize]) -> AutomatonEdges {
+        let mut subsets = vec![self.closure([initial].iter().cloned().collect())];
+        let mut indices: HashMap<BTreeSet<usize>, usize> = HashMap::new();
+        indices.insert(subsets[0].clone(), 0);
+        let mut dfa = Vec::<BTreeMap<String, usize>>::new();
+
+        let mut current = 0;
+        while current < subsets.len() {
+            let mut targets = BTreeMap::<String, BTreeSet<usize>>::new();
+            for state in &subsets[current] {
+                for (label, to) in &self.transitions[*state] {
+                    if let Some(label) = label {
+                        targets.entry(label.clone()).or_default().insert(*to);
+                    }
+                }
+            }
+
+            let mut row = BTreeMap::new();
+            for (label, target) in targets {
+                let target = self.closure(target);
+                let index = match indices.get(&target) {
+                    Some(index) => *index,
+                    None => {
+                        indices.insert(target.clone(), subsets.len());
+                        subsets.push(target);
+                        subsets.len() - 1
+                    }
+                };
+                row.insert(label, index);
+            }
+            dfa.push(row);
+            current += 1;
+        }
+
+        let accepting: Vec<bool> = subsets
+            .iter()
+            .map(|subset| finals.iter().any(|f| subset.contains(f)))
+            .collect();
+
+        minimize(&dfa, &accepting)
+    }
+}
+
+fn minimize(dfa: &[BTreeMap<String, usize>], accepting: &[bool]) -> AutomatonEdges {
+    let size = dfa.len();
+
+    let mut alive = accepting.to_vec();
+    let mut changing = true;
+    while changing {
+        changing = false;
+        for state in 0..size {
+            if !alive[state] && dfa[state].values().any(|to| alive[*to]) {
+                alive[state] = true;
+                changing = true;
+            }
+        }
+    }
+
+    if !alive[0] {
+        return (0, Vec::new(), Vec::new());
+    }
+
+    let mut classes: Vec<usize> = accepting.iter().map(|a| *a as usize).collect();
+    let mut count = 0;
+    loop {
+        let mut signatures = HashMap::<(usize, Vec<(&String, usize)>), usize>::new();
+        let mut refined = vec![0; size];
+        for state in (0..size).filter(|s| alive[*s]) {
+            let transitions = dfa[state]
+                .iter()
+                .filter(|(_, to)| alive[**to])
+                .map(|(label, to)| (label, classes[*to]))
+                .collect();
+            let next = signatures.len();
+            refined[state] = *signatures
+                .entry((classes[state], transitions))
+                .or_insert(next);
+        }
+
+        classes = refined;
+        if signatures.len() == count {
+            break;
+        }
+        count = signatures.len();
+    }
+
+    let mut numbering = HashMap::<usize, usize>::new();
+    numbering.insert(classes[0], 0);
+    let mut queue = VecDeque::new();
+    queue.push_back(0);
+    let mut finals = Vec::new();
+    let mut edges = Vec::new();
+    let mut visited = vec![false; count];
+
+    while let Some(state) = queue.pop_front() {
+        let from = numbering[&classes[state]];
+        if visited[from] {
+            continue;
+        }
+        visited[from] = true;
+        if accepting[state] {
+            finals.push(from);
+        }
+
+        for (label, to) in &dfa[state] {
+            if !alive[*to] {
+                continue;
+            }
+            let next = numbering.len();
+            let to_number = *numbering.entry(classes[*to]).or_insert(next);
+            edges.push((from, to_number, label.clone()));
+            queue.push_back(*to);
+        }
+    }
+
+    (0, finals, edges)
+}
+
+#[cfg(not(feature = "pyformlang"))]
+pub fn regex_to_edges(regex: &str) -> Result<AutomatonEdges> {
+    Ok(Regex::parse(regex)?.to_minimal_dfa())
+}
+
+#[cfg(feature = "pyformlang")]
+pub fn regex_to_edges(regex: &str) -> Result<AutomatonEdges> {
+    use pyo3::types::PyModule;
+    use pyo3::Python;
+    use std::str::from_utf8;
+
+    Python::with_gil(|py| -> Result<AutomatonEdges> {
+        let module = PyModule::from_code(
+            py,
+            from_utf8(include_bytes!("py/regex_to_edges.py"))?,
+            "a.compute.py",
+            "a",
+        )?;
+        let py_res: AutomatonEdges = module.call1("regex_to_edges", (regex,))?.extract()?;
+        Ok(py_res)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use anyhow::Result;
+
+    use super::*;
+
+    fn symbol(s: &str) -> Box<Regex> {
+        Box::new(Regex::Symbol(s.to_string()))
+    }
+
+    fn size(edges: &AutomatonEdges) -> usize {
+        let (initial, finals, edges) = edges;
+        let mut states: HashSet<usize> = finals.iter().cloned().collect();
+        states.insert(*initial);
+        for (from, to, _) in edges {
+            states.insert(*from);
+            states.insert(*to);
+        }
+        states.len()
+    }
+
+    #[test]
+    fn parse() -> Result<()> {
+        assert_eq!(Regex::Epsilon, Regex::parse("epsilon")?);
+        assert_eq!(Regex::Epsilon, Regex::parse("")?);
+        assert_eq!(
+            Regex::Union(
+                Box::new(Regex::Concat(symbol("ab"), Box::new(Regex::Star(symbol("c"))))),
+                symbol("d")
+            ),
+            Regex::parse("ab c* | d")?
+        );
+        assert_eq!(
+            Regex::Concat(symbol("a"), Box::new(Regex::Union(symbol("b"), symbol("c")))),
+            Regex::parse("a.(b+c)")?
+        );
+        assert!(Regex::parse("(a").is_err());
+        assert!(Regex::parse("a |").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn minimal() -> Result<()> {
+        assert_eq!((0, vec![0], vec![]), Regex::parse("$")?.to_minimal_dfa());
+        assert_eq!(1, size(&Regex::parse("(a|b)*")?.to_minimal_dfa()));
+        assert_eq!(3, size(&Regex::parse("a b | a c")?.to_minimal_dfa()));
+        assert_eq!(2, size(&Regex::parse("(a b)* | (a b)* a b")?.to_minimal_dfa()));
+        Ok(())
+    }
+
+    #[test]
+    fn numbering() -> Result<()> {
+        assert_eq!(
+            (0, vec![2], vec![(0, 1, "a".to_string()), (1, 2, "b".to_string())]),
+            Regex::parse("a b")?.to_minimal_dfa()
+        );
+        Ok(())
+    }
+}
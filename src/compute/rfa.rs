@@ -2,17 +2,17 @@ use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::io::{BufReader, Read};
 use std::path::Path;
-use std::str::from_utf8;
 
 use anyhow::Result;
-use pyo3::Python;
-use pyo3::types::PyModule;
 
 use graphblas::*;
 
 use super::cfg::{ContextFreeGrammar, ContextFreeResult};
+use super::cnf::read_cfg_in_cnf;
 use super::dfa::Dfa;
 use super::graph::{BooleanMatrix, Edge, Ends, ExtractPairs, Graph};
+use super::limits::{ExecutionLimits, LimitExceeded};
+use super::regex::regex_to_edges;
 
 pub struct Rfa {
     pub(crate) dfa: Dfa,
@@ -34,19 +34,8 @@ impl Rfa {
     pub fn from_text_without_regex(text: &str) -> Result<Rfa> {
         let converted = ContextFreeGrammar::convert(text.lines().map(Ok))?;
 
-        let (initial, nonterminals, productions, produces_epsilon) = Python::with_gil(
-            |py| -> Result<(String, Vec<String>, Vec<(String, Vec<String>)>, bool)> {
-                let module = PyModule::from_code(
-                    py,
-                    from_utf8(include_bytes!("py/read_cfg_in_cnf.py"))?,
-                    "a.compute.py",
-                    "a",
-                )?;
-                Ok(module
-                    .call1("parse_cfg", (converted.as_str(),))?
-                    .extract()?)
-            },
-        )?;
+        let (initial, nonterminals, productions, produces_epsilon) =
+            read_cfg_in_cnf(converted.as_str())?;
 
         let mut edges = Vec::<Edge>::new();
         let mut initials = HashSet::<usize>::new();
@@ -141,18 +130,7 @@ impl Rfa {
                 .take_while(|c| !c.is_whitespace())
                 .collect();
 
-            let (line_initial, line_finals, line_edges) =
-                Python::with_gil(|py| -> Result<(usize, Vec<usize>, Vec<Edge>)> {
-                    let module = PyModule::from_code(
-                        py,
-                        from_utf8(include_bytes!("py/regex_to_edges.py"))?,
-                        "a.compute.py",
-                        "a",
-                    )?;
-                    let py_res: (usize, Vec<usize>, Vec<Edge>) =
-                        module.call1("regex_to_edges", (body,))?.extract()?;
-                    Ok(py_res)
-                })?;
+            let (line_initial, line_finals, line_edges) = regex_to_edges(body)?;
 
             let mut max = line_initial;
 
@@ -204,7 +182,12 @@ impl Rfa {
 }
 
 impl Graph {
-    pub fn cfpq_tensor_product(&self, rfa: &Rfa) -> ResultTensors {
+    pub fn cfpq_tensor_product(
+        &self,
+        rfa: &Rfa,
+        limits: &ExecutionLimits,
+    ) -> Result<ResultTensors, LimitExceeded> {
+        let mut progress = limits.start("tensor product CFPQ");
         let mut m2 = self.clone();
 
         for nonterminal in &rfa.with_epsilon {
@@ -216,9 +199,11 @@ impl Graph {
 
         let mut changing = true;
         while changing {
+            let largest = m2.matrices.values().map(|m| m.nvals()).max().unwrap_or(0);
+            progress.iteration(largest.max(rfa.dfa.graph.kronecker_nvals(&m2)))?;
             changing = false;
             let intersection = rfa.dfa.graph.kronecker(&m2);
-            for (from, to) in intersection.reachable_pairs() {
+            for (from, to) in intersection.reachable_pairs(limits)? {
                 let ref rfa_c = (from / self.size, to / self.size);
                 let (rfa_from, rfa_to) = rfa_c;
                 if rfa.dfa.initials.contains(rfa_from) && rfa.dfa.finals.contains(rfa_to) {
@@ -233,10 +218,10 @@ impl Graph {
             }
         }
 
-        ResultTensors {
+        Ok(ResultTensors {
             map: m2.matrices,
             nonterminals: rfa.nonterminals.clone(),
-        }
+        })
     }
 }
 
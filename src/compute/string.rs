@@ -0,0 +1,68 @@
+/// Reads a string literal at the start of `text`, returns its value and the rest of `text`.
+/// The escapes are `\"`, `\\`, `\n` and `\t`, any other character stands for itself.
+pub fn read_literal(text: &str) -> Option<(String, &str)> {
+    let mut chars = text.char_indices();
+    if chars.next()? != (0, '"') {
+        return None;
+    }
+    let mut value = String::new();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Some((value, &text[i + 1..])),
+            '\\' => value.push(match chars.next()?.1 {
+                '"' => '"',
+                '\\' => '\\',
+                'n' => '\n',
+                't' => '\t',
+                _ => return None,
+            }),
+            c => value.push(c),
+        }
+    }
+    None
+}
+
+/// String literal for `value`, reading it gives `value` back.
+pub fn literal(value: &str) -> String {
+    let mut literal = String::with_capacity(value.len() + 2);
+    literal.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => literal.push_str("\\\""),
+            '\\' => literal.push_str("\\\\"),
+            '\n' => literal.push_str("\\n"),
+            '\t' => literal.push_str("\\t"),
+            c => literal.push(c),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        for value in &[
+            "",
+            "a",
+            "rdf:type",
+            "has part",
+            "say \"hi\"\n",
+            "\\d+\t",
+            "часть",
+        ] {
+            let text = format!("{} rest", literal(value));
+            assert_eq!(Some((value.to_string(), " rest")), read_literal(&text));
+        }
+    }
+
+    #[test]
+    fn invalid() {
+        for text in &["a", "\"a", "\"a\\\"", "\"\\d\""] {
+            assert_eq!(None, read_literal(text));
+        }
+    }
+}
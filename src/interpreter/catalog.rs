@@ -0,0 +1,220 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Error, Result};
+use serde::Deserialize;
+
+use crate::compute::graph::Graph;
+
+/// File of a catalog directory listing its graphs.
+pub const MANIFEST: &str = "catalog.json";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GraphFormat {
+    /// `from label to` lines.
+    #[default]
+    Edges,
+    /// `from,label,to` lines.
+    Csv,
+}
+
+/// A graph of the manifest, every field may be omitted.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GraphEntry {
+    /// Path relative to the catalog directory, the name of the graph by default.
+    file: Option<String>,
+    format: GraphFormat,
+    /// New names of labels of the file, scripts use them instead.
+    labels: HashMap<String, String>,
+    /// Initials of the graph when a script does not give them, every vertex by default.
+    pub initials: Option<Vec<usize>>,
+    pub finals: Option<Vec<usize>>,
+    /// Number of vertices, for a file whose last vertices have no edges.
+    vertices: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Manifest {
+    graphs: BTreeMap<String, GraphEntry>,
+}
+
+/// Graphs of a `connect`ed directory. With a manifest the graphs are the ones it lists,
+/// otherwise every file of the directory is a graph in the edge list format named by the file.
+/// Graphs are read on first use and kept.
+pub struct GraphCatalog {
+    directory: PathBuf,
+    manifest: Option<Manifest>,
+    loaded: HashMap<String, (PathBuf, Graph)>,
+}
+
+impl GraphCatalog {
+    /// Reads the manifest of the directory if it has one, the graphs are not read.
+    pub fn open(directory: PathBuf) -> Result<GraphCatalog> {
+        let path = directory.join(MANIFEST);
+        let manifest = if path.is_file() {
+            let reader = BufReader::new(File::open(&path)?);
+            let manifest = serde_json::from_reader(reader)
+                .map_err(|e| Error::msg(format!("invalid manifest {:?}: {}", path, e)))?;
+            Some(manifest)
+        } else {
+            None
+        };
+        Ok(GraphCatalog {
+            directory,
+            manifest,
+            loaded: HashMap::new(),
+        })
+    }
+
+    /// Names of the graphs, sorted.
+    pub fn names(&self) -> Result<Vec<String>> {
+        if let Some(manifest) = &self.manifest {
+            return Ok(manifest.graphs.keys().cloned().collect());
+        }
+        let entries = self.directory.read_dir().map_err(|e| {
+            Error::msg(format!("cannot list graphs of {:?}: {}", self.directory, e))
+        })?;
+        let mut names = Vec::new();
+        for entry in entries {
+            let entry = entry?;
+            if entry.file_type()?.is_file() {
+                names.push(entry.file_name().to_string_lossy().into_owned());
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    /// The manifest entry of a graph, `None` for a catalog without a manifest.
+    pub fn entry(&self, name: &str) -> Result<Option<&GraphEntry>> {
+        match &self.manifest {
+            Some(manifest) => manifest.graphs.get(name).map(Some).ok_or_else(|| {
+                Error::msg(format!(
+                    "graph '{}' is not in the catalog {:?}",
+                    name, self.directory
+                ))
+            }),
+            None => Ok(None),
+        }
+    }
+
+    /// Reads the graph on first use, its labels are renamed as the manifest says.
+    pub fn graph(&mut self, name: &str) -> Result<&Graph> {
+        if !self.loaded.contains_key(name) {
+            let default = GraphEntry::default();
+            let entry = self.entry(name)?.unwrap_or(&default);
+            let path = self.directory.join(entry.file.as_deref().unwrap_or(name));
+            let graph = match entry.format {
+                GraphFormat::Edges => Graph::read_from(&path),
+                GraphFormat::Csv => Graph::read_csv_from(&path),
+            };
+            let mut graph = graph.map_err(|e| {
+                Error::msg(format!("cannot load graph '{}' from {:?}: {}", name, path, e))
+            })?;
+            let mut origins: HashMap<String, String> = HashMap::new();
+            let mut matrices = HashMap::new();
+            for (label, matrix) in graph.matrices.drain() {
+                let renamed = entry.labels.get(&label).unwrap_or(&label).clone();
+                if let Some(other) = origins.insert(renamed.clone(), label.clone()) {
+                    return Err(Error::msg(format!(
+                        "labels '{}' and '{}' of graph '{}' are both named '{}'",
+                        other, label, name, renamed
+                    )));
+                }
+                matrices.insert(renamed, matrix);
+            }
+            graph.matrices = matrices;
+            if let Some(vertices) = entry.vertices {
+                if vertices < graph.size {
+                    return Err(Error::msg(format!(
+                        "graph '{}' has {} vertices, its file uses {}",
+                        name, vertices, graph.size
+                    )));
+                }
+                graph = graph.resized(vertices);
+            }
+            self.loaded.insert(name.to_string(), (path, graph));
+        }
+        Ok(&self.loaded[name].1)
+    }
+
+    /// Graphs read so far with their files.
+    pub fn loaded(&self) -> impl Iterator<Item = (&Path, &Graph)> {
+        self.loaded
+            .values()
+            .map(|(path, graph)| (path.as_path(), graph))
+    }
+
+    /// Drops the graphs read from `path`, they are read again on next use.
+    pub fn forget(&mut self, path: &Path) {
+        self.loaded.retain(|_, (file, _)| file != path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::compute::graph::ExtractPairs;
+
+    use super::*;
+
+    fn test_data(directory: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("test_data")
+            .join(directory)
+    }
+
+    #[test]
+    fn manifest() -> Result<()> {
+        let mut catalog = GraphCatalog::open(test_data("catalog"))?;
+        assert_eq!(
+            vec!["chain", "clash", "padded", "people", "swapped", "truncated"],
+            catalog.names()?
+        );
+        assert_eq!(Some(vec![0]), catalog.entry("people")?.unwrap().initials);
+        assert!(catalog.entry("missing").is_err());
+
+        let people = catalog.graph("people")?;
+        assert_eq!(vec!["knows", "likes, a lot"], people.alphabet());
+        let mut knows = people.get("knows").unwrap().extract_pairs();
+        knows.sort();
+        assert_eq!(vec![(0, 1), (1, 2)], knows);
+        assert_eq!(1, catalog.loaded().count());
+
+        assert_eq!(8, catalog.graph("padded")?.size);
+        assert!(catalog.graph("truncated").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn renamed_labels() -> Result<()> {
+        let mut catalog = GraphCatalog::open(test_data("catalog"))?;
+        let swapped = catalog.graph("swapped")?;
+        let mut a = swapped.get("a").unwrap().extract_pairs();
+        a.sort();
+        assert_eq!(vec![(1, 4), (2, 3), (3, 0)], a);
+        let mut b = swapped.get("b").unwrap().extract_pairs();
+        b.sort();
+        assert_eq!(vec![(0, 1), (1, 2)], b);
+        assert!(catalog.graph("clash").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn directory() -> Result<()> {
+        let mut catalog = GraphCatalog::open(test_data("graphs"))?;
+        assert!(catalog.names()?.contains(&"graph1".to_string()));
+        assert!(catalog.entry("graph1")?.is_none());
+        assert_eq!(2, catalog.graph("graph1")?.size);
+
+        catalog.forget(&test_data("graphs").join("graph1"));
+        assert_eq!(0, catalog.loaded().count());
+        Ok(())
+    }
+}
@@ -0,0 +1,217 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{Error, Result};
+use graphblas::{Get, *};
+use regex::Regex;
+
+use crate::compute::dfa::Dfa;
+use crate::compute::graph::{BooleanMatrix, Ends};
+use crate::syntax::ast::*;
+
+/// Compiled expressions of the `label matches` conditions, by their text.
+pub struct LabelRegexes<'a>(HashMap<&'a str, Regex>);
+
+impl<'a> LabelRegexes<'a> {
+    pub fn new(conditions: &[&'a BoolExpr]) -> Result<LabelRegexes<'a>> {
+        let mut patterns = Vec::new();
+        for condition in conditions {
+            label_patterns(condition, &mut patterns);
+        }
+        let mut regexes = HashMap::new();
+        for pattern in patterns {
+            let regex = Regex::new(&format!("^(?:{})$", pattern))
+                .map_err(|e| Error::msg(format!("invalid label pattern \"{}\": {}", pattern, e)))?;
+            regexes.insert(pattern, regex);
+        }
+        Ok(LabelRegexes(regexes))
+    }
+
+    fn matches(&self, pattern: &str, label: &str) -> bool {
+        self.0[pattern].is_match(label)
+    }
+}
+
+fn label_patterns<'a>(condition: &'a BoolExpr, patterns: &mut Vec<&'a str>) {
+    match condition {
+        LabelMatches(pattern) => patterns.push(pattern),
+        And(b1, b2) | Or(b1, b2) => {
+            label_patterns(b1, patterns);
+            label_patterns(b2, patterns);
+        }
+        Not(b) => label_patterns(b, patterns),
+        _ => {}
+    }
+}
+
+impl BoolExpr {
+    /// Evaluates `and`, `or` and `not`, leaving the conditions themselves to `condition`.
+    fn holds<F: Fn(&BoolExpr) -> bool>(&self, condition: &F) -> bool {
+        match self {
+            And(b1, b2) => b1.holds(condition) && b2.holds(condition),
+            Or(b1, b2) => b1.holds(condition) || b2.holds(condition),
+            Not(b) => !b.holds(condition),
+            b => condition(b),
+        }
+    }
+
+    /// Checks an edge of `dfa.graph`, `dfa.initials` and `dfa.finals` are the ends
+    /// given to the source graph expression.
+    pub fn satisfied(&self, dfa: &Dfa, regexes: &LabelRegexes, (from, to): Ends) -> bool {
+        let labeled = |label: &str| {
+            dfa.graph
+                .get(label)
+                .is_some_and(|m| m.get(from as u64, to as u64).is_some())
+        };
+        self.holds(&|condition| match condition {
+            LabelIs(label) => labeled(label),
+            LabelIn(labels) => labels.iter().any(|l| labeled(l)),
+            LabelMatches(pattern) => dfa
+                .graph
+                .matrices
+                .keys()
+                .any(|l| regexes.matches(pattern, l) && labeled(l)),
+            BeginIs(variant) => variant.vertices(dfa).contains(&from),
+            EndIs(variant) => variant.vertices(dfa).contains(&to),
+            BeginIn(vertices) => vertices.contains(from),
+            EndIn(vertices) => vertices.contains(to),
+            BeginCompare(comparison, n) => comparison.holds(from, *n),
+            EndCompare(comparison, n) => comparison.holds(to, *n),
+            SelfLoop => from == to,
+            _ => false,
+        })
+    }
+
+    /// Entries of `matrix`, edges of `dfa.graph` labeled `label`, that satisfy the condition
+    /// with `label` as their only label. Computed with masks and products by diagonal matrices
+    /// of the vertices that satisfy the conditions on ends.
+    pub fn select(
+        &self,
+        dfa: &Dfa,
+        regexes: &LabelRegexes,
+        label: &str,
+        matrix: &BooleanMatrix,
+    ) -> BooleanMatrix {
+        let graph = &dfa.graph;
+        let diagonal = |holds: &dyn Fn(usize) -> bool| {
+            let vertices: Vec<Ends> = (0..graph.size).filter(|v| holds(*v)).map(|v| (v, v)).collect();
+            graph.pairs_matrix(&vertices)
+        };
+        let mut selected = graph.new_matrix.call(());
+        match self {
+            And(b1, b2) => {
+                let first = b1.select(dfa, regexes, label, matrix);
+                return b2.select(dfa, regexes, label, &first);
+            }
+            Or(b1, b2) => {
+                selected = b1.select(dfa, regexes, label, matrix);
+                selected.accumulate_apply(
+                    BinaryOp::<bool, bool, bool>::lor(),
+                    UnaryOp::<bool, bool>::identity(),
+                    &b2.select(dfa, regexes, label, matrix),
+                );
+            }
+            Not(b) => selected.assign_apply_masked(
+                &b.select(dfa, regexes, label, matrix),
+                UnaryOp::<bool, bool>::identity(),
+                matrix,
+                Descriptor::replace_complement_mask(),
+            ),
+            LabelIs(_) | LabelIn(_) | LabelMatches(_) => {
+                if self.satisfied_by_label(regexes, label) {
+                    return matrix.clone();
+                }
+            }
+            BeginIs(_) | BeginIn(_) | BeginCompare(..) => selected.assign_mxm(
+                Semiring::<bool>::lor_land(),
+                &diagonal(&|v| self.satisfied(dfa, regexes, (v, v))),
+                matrix,
+            ),
+            EndIs(_) | EndIn(_) | EndCompare(..) => selected.assign_mxm(
+                Semiring::<bool>::lor_land(),
+                matrix,
+                &diagonal(&|v| self.satisfied(dfa, regexes, (v, v))),
+            ),
+            SelfLoop => selected.assign_apply_masked(
+                &diagonal(&|_| true),
+                UnaryOp::<bool, bool>::identity(),
+                matrix,
+                Descriptor::default(),
+            ),
+            _ => {}
+        }
+        selected
+    }
+
+    pub fn satisfied_by_vertex(&self, dfa: &Dfa, vertex: usize) -> bool {
+        self.holds(&|condition| match condition {
+            VertexIs(variant) => variant.vertices(dfa).contains(&vertex),
+            _ => false,
+        })
+    }
+
+    pub fn satisfied_by_label(&self, regexes: &LabelRegexes, label: &str) -> bool {
+        self.holds(&|condition| match condition {
+            LabelIs(l) => l == label,
+            LabelIn(labels) => labels.iter().any(|l| l == label),
+            LabelMatches(pattern) => regexes.matches(pattern, label),
+            _ => false,
+        })
+    }
+}
+
+impl VertexVariant {
+    fn vertices<'a>(&self, dfa: &'a Dfa) -> &'a HashSet<usize> {
+        match self {
+            VertexVariant::Initial => &dfa.initials,
+            VertexVariant::Final => &dfa.finals,
+        }
+    }
+}
+
+impl Vertices {
+    fn contains(&self, vertex: usize) -> bool {
+        match self {
+            Set(set) => set.contains(&vertex),
+            Range { from, to } => (*from..=*to).contains(&vertex),
+            EmptySet => false,
+            Reachable(..) | Of(_) => {
+                unreachable!("vertices found by a query are only parsed for graph ends")
+            }
+        }
+    }
+}
+
+impl Comparison {
+    fn holds(self, a: usize, b: usize) -> bool {
+        match self {
+            Comparison::Less => a < b,
+            Comparison::LessOrEqual => a <= b,
+            Comparison::Greater => a > b,
+            Comparison::GreaterOrEqual => a >= b,
+            Comparison::Equal => a == b,
+            Comparison::NotEqual => a != b,
+        }
+    }
+}
+
+impl ListExpr {
+    /// What is listed: `Edges`, `VertexList`, `Labels` or `Pairs`.
+    pub fn object(&self) -> &ListExpr {
+        match self {
+            Filter(list, _) => list.object(),
+            object => object,
+        }
+    }
+
+    /// Conditions of all nested filters, an item is listed if it satisfies each of them.
+    pub fn conditions(&self) -> Vec<&BoolExpr> {
+        match self {
+            Filter(list, condition) => {
+                let mut conditions = list.conditions();
+                conditions.push(condition);
+                conditions
+            }
+            _ => Vec::new(),
+        }
+    }
+}
@@ -0,0 +1,1547 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Error, Result};
+use serde::Serialize;
+
+use graphblas::MatrixActions;
+
+use crate::compute::cfg::ContextFreeResult;
+use crate::compute::dfa::Dfa;
+use crate::compute::graph::{BooleanMatrix, Degrees, Ends, ExtractPairs, Graph};
+use crate::compute::limits::ExecutionLimits;
+use crate::compute::rfa::Rfa;
+use crate::interpreter::catalog::GraphCatalog;
+use crate::interpreter::filter::LabelRegexes;
+use crate::interpreter::pattern::{
+    expand, expand_definitions, pattern_labels, recursive_definitions, refers_to, Macros,
+    QUERY_NONTERMINAL,
+};
+use crate::interpreter::plan::{
+    filtered_labels, intersection_order, query_labels, reordering, Estimate,
+};
+use crate::output::Format;
+use crate::syntax::ast::*;
+use crate::syntax::build_ast;
+
+pub mod catalog;
+mod filter;
+pub mod pattern;
+mod plan;
+
+pub struct Interpreter {
+    base: PathBuf,
+    /// Directory of the connected catalog.
+    database: Option<PathBuf>,
+    catalogs: HashMap<PathBuf, GraphCatalog>,
+    patterns: HashMap<String, Pattern>,
+    macros: Macros,
+    /// Expressions of `define ... as graph` statements.
+    graph_definitions: HashMap<String, GraphExpr>,
+    /// Values of defined graphs by their expressions, each one is computed once.
+    computed: HashMap<GraphExpr, Dfa>,
+    /// Bounds of the closures computed for `get` statements.
+    limits: ExecutionLimits,
+    /// Scripts being run by `include`, to detect cycles.
+    including: Vec<PathBuf>,
+}
+
+/// Result of a statement, collections are kept sorted so that every output format is deterministic.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Value {
+    Edges(Vec<Ends>),
+    Count(usize),
+    Vertices(Vec<usize>),
+    Labels(Vec<String>),
+    /// Pairs derivable from each nonterminal of a context-free query.
+    Pairs(BTreeMap<String, Vec<Ends>>),
+    /// Numbers of edges by label.
+    Counts(BTreeMap<String, usize>),
+    /// Numbers of edges by their begin or end vertex.
+    #[serde(rename = "vertex_counts")]
+    VertexCounts(BTreeMap<usize, usize>),
+    /// Names of the graphs of a catalog.
+    Graphs(Vec<String>),
+    /// Steps of an `explain`, one per line.
+    Plan(Vec<String>),
+}
+
+impl Value {
+    pub fn pairs<R: ContextFreeResult>(result: &R) -> Value {
+        Value::Pairs(
+            result
+                .nonterminals()
+                .iter()
+                .map(|nonterminal| {
+                    let mut edges = result.reachable_edges(nonterminal);
+                    edges.sort();
+                    (nonterminal.clone(), edges)
+                })
+                .collect(),
+        )
+    }
+
+    pub fn counts(counts: HashMap<String, usize>) -> Value {
+        Value::Counts(counts.into_iter().collect())
+    }
+}
+
+impl Interpreter {
+    pub fn new<P: AsRef<Path>>(base: P) -> Interpreter {
+        Interpreter {
+            base: base.as_ref().to_path_buf(),
+            database: None,
+            catalogs: HashMap::new(),
+            patterns: HashMap::new(),
+            macros: HashMap::new(),
+            graph_definitions: HashMap::new(),
+            computed: HashMap::new(),
+            limits: ExecutionLimits::default(),
+            including: Vec::new(),
+        }
+    }
+
+    /// Limits every following statement is executed within, a statement that exceeds them
+    /// fails with a `LimitExceeded` error.
+    pub fn set_limits(&mut self, limits: ExecutionLimits) {
+        self.limits = limits;
+    }
+
+    /// Graphs loaded so far, by the files they were read from.
+    pub fn graphs(&self) -> Vec<(&Path, &Graph)> {
+        let mut graphs: Vec<(&Path, &Graph)> = self
+            .catalogs
+            .values()
+            .flat_map(GraphCatalog::loaded)
+            .collect();
+        graphs.sort_by_key(|(path, _)| *path);
+        graphs
+    }
+
+    /// `define` statements giving the current definitions, sorted by name.
+    pub fn definitions(&self) -> Vec<Statement> {
+        let mut definitions: Vec<(&String, Statement)> = self
+            .patterns
+            .iter()
+            .map(|(name, pattern)| (name, Define(name.clone(), pattern.clone())))
+            .chain(self.macros.iter().map(|(name, (parameters, pattern))| {
+                let statement = Macro(name.clone(), parameters.clone(), pattern.clone());
+                (name, statement)
+            }))
+            .chain(
+                self.graph_definitions
+                    .iter()
+                    .map(|(name, expr)| (name, DefineGraph(name.clone(), expr.clone()))),
+            )
+            .collect();
+        definitions.sort_by_key(|(name, _)| *name);
+        definitions.into_iter().map(|(_, statement)| statement).collect()
+    }
+
+    pub fn run(&mut self, script: &Script) -> Result<Vec<Value>> {
+        let Sequence(statements) = script;
+        let mut values = Vec::new();
+        for statement in statements {
+            values.extend(self.execute(statement)?);
+        }
+        Ok(values)
+    }
+
+    /// Values of `get` statements, an `include` gives the values of the included script.
+    pub fn execute(&mut self, statement: &Statement) -> Result<Vec<Value>> {
+        match statement {
+            Connect(path) => {
+                let directory = self.base.join(path.join("/"));
+                if !self.catalogs.contains_key(&directory) {
+                    let catalog = GraphCatalog::open(directory.clone())?;
+                    self.catalogs.insert(directory.clone(), catalog);
+                }
+                if self.database.as_ref() != Some(&directory) {
+                    self.computed.clear();
+                }
+                self.database = Some(directory);
+                Ok(Vec::new())
+            }
+            Define(name, pattern) => {
+                self.macros.remove(name);
+                self.patterns.insert(name.clone(), pattern.clone());
+                self.forget_queries(name);
+                Ok(Vec::new())
+            }
+            Macro(name, parameters, pattern) => {
+                self.patterns.remove(name);
+                self.macros
+                    .insert(name.clone(), (parameters.clone(), pattern.clone()));
+                self.forget_queries(name);
+                Ok(Vec::new())
+            }
+            DefineGraph(name, expr) => {
+                if uses(expr, name, &self.graph_definitions) {
+                    return Err(Error::msg(format!("graph '{}' is defined by itself", name)));
+                }
+                self.computed_graph(expr)?;
+                let definitions = &self.graph_definitions;
+                self.computed
+                    .retain(|expr, _| !uses(expr, name, definitions));
+                self.graph_definitions.insert(name.clone(), expr.clone());
+                Ok(Vec::new())
+            }
+            Get(object, source) => Ok(vec![self.get(object, source)?]),
+            Explain(object, source) => Ok(vec![Value::Plan(self.explain(object, source)?)]),
+            Save(object, source, file) => {
+                let value = self.get(object, source)?;
+                let path = self.base.join(file);
+                let mut output = self.create(&path)?;
+                Format::for_path(&path).write(&[value], &mut output)?;
+                output.flush()?;
+                Ok(Vec::new())
+            }
+            Include(file) => self.include(file),
+            ListGraphs => Ok(vec![Value::Graphs(self.catalog("list graphs")?.names()?)]),
+            SaveGraph(source, file) => {
+                let graph = match source {
+                    Direct(expr) => self.graph_expr(expr, None)?.graph,
+                    Apply(pattern, _) => {
+                        let label = match pattern {
+                            Var(name) | Term(name) => name.clone(),
+                            pattern => pattern.to_string(),
+                        };
+                        let edges = self.source(source, None)?.edges();
+                        Graph::from_edges(edges.into_iter().map(|(from, to)| Ok((from, to, label.clone()))))?
+                    }
+                };
+                let path = self.base.join(file);
+                let mut output = self.create(&path)?;
+                graph.write_to(&mut output)?;
+                output.flush()?;
+                for catalog in self.catalogs.values_mut() {
+                    catalog.forget(&path);
+                }
+                self.computed.clear();
+                Ok(Vec::new())
+            }
+        }
+    }
+
+    /// Runs a script file, paths in it are relative to its directory.
+    /// Errors are prefixed with the path of the file they come from.
+    pub fn include<P: AsRef<Path>>(&mut self, file: P) -> Result<Vec<Value>> {
+        let path = self.base.join(file);
+        let canonical = path
+            .canonicalize()
+            .map_err(|e| Error::msg(format!("cannot include {:?}: {}", path, e)))?;
+        if self.including.contains(&canonical) {
+            return Err(Error::msg(format!("{} includes itself", path.display())));
+        }
+        let text = std::fs::read_to_string(&path)
+            .map_err(|e| Error::msg(format!("cannot include {:?}: {}", path, e)))?;
+        let script =
+            build_ast(&text).map_err(|e| Error::msg(format!("{}:{}", path.display(), e)))?;
+
+        let directory = canonical.parent().map(Path::to_path_buf).unwrap_or_default();
+        let base = std::mem::replace(&mut self.base, directory);
+        self.including.push(canonical);
+        let values = self.run(&script);
+        self.including.pop();
+        self.base = base;
+        values.map_err(|e| Error::msg(format!("{}: {}", path.display(), e)))
+    }
+
+    fn get(&mut self, object: &ObjectExpr, source: &Source) -> Result<Value> {
+        let mut selection = self.source(source, Some(object))?;
+        match object {
+            List(list) => selection.list(list),
+            Count(list) => Ok(Value::Count(selection.count(list)?)),
+            CountBy(list, key) => selection.count_by(list, *key),
+            Distinct(key, list) => selection.distinct(*key, list),
+        }
+    }
+
+    fn create(&self, path: &Path) -> Result<BufWriter<File>> {
+        let file = File::create(path)
+            .map_err(|e| Error::msg(format!("cannot write to {:?}: {}", path, e)))?;
+        Ok(BufWriter::new(file))
+    }
+
+    /// Graphs are restricted to the labels `object` depends on, all the found pairs
+    /// are selected without it.
+    fn source(&mut self, source: &Source, object: Option<&ObjectExpr>) -> Result<Selection> {
+        Ok(match source {
+            Direct(expr) => {
+                let labels = object.and_then(filtered_labels);
+                Selection {
+                    dfa: self.graph_expr(expr, labels.as_ref())?,
+                    pairs: None,
+                    limits: self.limits.clone(),
+                }
+            }
+            Apply(pattern, expr) => {
+                let pattern = &expand(pattern, &self.macros)?;
+                let patterns = expand_definitions(pattern, &self.patterns, &self.macros)?;
+                let recursive = recursive_definitions(pattern, &patterns)?;
+                let labels = query_labels(object, pattern_labels(pattern, &patterns));
+                let dfa = self.graph_expr(expr, labels.as_ref())?;
+                let alphabet = dfa.graph.alphabet();
+                let pairs: Vec<Ends> = if recursive.is_empty() {
+                    let request = Dfa::from_pattern(pattern, &patterns, &alphabet)?;
+                    let graph = dfa.graph.with_inverses(request.graph.matrices.keys());
+                    let mut pairs = graph.rpq(&request, &self.limits)?;
+                    // The empty word connects every vertex to itself, as `with_epsilon` does for CFPQ
+                    if request.accepts(&[]) {
+                        pairs.extend((0..graph.size).map(|v| (v, v)));
+                    }
+                    pairs.into_iter().collect()
+                } else {
+                    let rfa = Rfa::from_pattern(pattern, &patterns, &recursive, &alphabet)?;
+                    let graph = dfa.graph.with_inverses(rfa.dfa.graph.matrices.keys());
+                    graph
+                        .cfpq_tensor_product(&rfa, &self.limits)?
+                        .reachable_edges(QUERY_NONTERMINAL)
+                };
+                let pairs = pairs
+                    .into_iter()
+                    .filter(|(from, to)| dfa.initials.contains(from) && dfa.finals.contains(to))
+                    .collect();
+                Selection {
+                    dfa,
+                    pairs: Some(pairs),
+                    limits: self.limits.clone(),
+                }
+            }
+        })
+    }
+
+    /// Graphs of the expression with the edges of `labels` only, all of them for `None`.
+    /// An intersection is computed starting with its smallest operands.
+    fn graph_expr(&mut self, expr: &GraphExpr, labels: Option<&HashSet<String>>) -> Result<Dfa> {
+        match expr {
+            Intersection(graphs) => {
+                let dfas = graphs
+                    .iter()
+                    .map(|graph| self.simple_graph(graph, labels))
+                    .collect::<Result<Vec<Dfa>>>()?;
+                let sizes: Vec<usize> = dfas.iter().map(|dfa| dfa.graph.size).collect();
+                let estimates: Vec<Estimate> =
+                    dfas.iter().map(|dfa| Estimate::of(&dfa.graph)).collect();
+                let order = intersection_order(&estimates);
+
+                let mut dfas: Vec<(usize, Dfa)> = dfas.into_iter().enumerate().collect();
+                dfas.sort_by_key(|(i, _)| order.iter().position(|j| j == i));
+                let mut result: Option<Dfa> = None;
+                for (_, dfa) in dfas {
+                    result = Some(match result {
+                        Some(result) => result.intersection(&dfa, &self.limits)?,
+                        None => dfa,
+                    });
+                }
+                let result = result.ok_or_else(|| Error::msg("empty intersection"))?;
+                if order.windows(2).all(|w| w[0] < w[1]) {
+                    Ok(result)
+                } else {
+                    Ok(result.renumbered(reordering(&sizes, &order)))
+                }
+            }
+            Union(e1, e2) => {
+                let (dfa1, dfa2) = (self.graph_expr(e1, labels)?, self.graph_expr(e2, labels)?);
+                Ok(Dfa {
+                    graph: dfa1.graph.union(&dfa2.graph),
+                    initials: dfa1.initials.union(&dfa2.initials).cloned().collect(),
+                    finals: dfa1.finals.union(&dfa2.finals).cloned().collect(),
+                })
+            }
+            Difference(e1, e2) => {
+                let (dfa1, dfa2) = (self.graph_expr(e1, labels)?, self.graph_expr(e2, labels)?);
+                Ok(Dfa {
+                    graph: dfa1.graph.difference(&dfa2.graph),
+                    ..dfa1
+                })
+            }
+        }
+    }
+
+    fn simple_graph(
+        &mut self,
+        graph: &SimpleGraph,
+        labels: Option<&HashSet<String>>,
+    ) -> Result<Dfa> {
+        match graph {
+            GraphName(name) => {
+                let omitted = Set(Vec::new());
+                self.named_graph(name, &omitted, &omitted, labels)
+            }
+            WithEnds {
+                initials,
+                finals,
+                graph,
+            } => self.named_graph(graph, initials, finals, labels),
+            Reverse(graph) => {
+                let dfa = self.simple_graph(graph, labels)?;
+                Ok(Dfa {
+                    graph: dfa.graph.reversed(),
+                    ..dfa
+                })
+            }
+            Restricted(graph, restriction) => {
+                let dfa = self.simple_graph(graph, labels)?;
+                Ok(Dfa {
+                    graph: dfa.graph.restricted(restriction),
+                    ..dfa
+                })
+            }
+            Nested(expr) => self.graph_expr(expr, labels),
+        }
+    }
+
+    /// A defined graph or a graph of the catalog, omitted ends are the ones of its definition
+    /// or of its manifest entry.
+    fn named_graph(
+        &mut self,
+        name: &str,
+        initials: &Vertices,
+        finals: &Vertices,
+        labels: Option<&HashSet<String>>,
+    ) -> Result<Dfa> {
+        let dfa = self.stored_graph(name, labels)?;
+        let size = dfa.graph.size;
+        Ok(Dfa {
+            initials: self.vertices(name, initials, dfa.initials, size)?,
+            finals: self.vertices(name, finals, dfa.finals, size)?,
+            graph: dfa.graph,
+        })
+    }
+
+    /// A defined graph or a graph of the catalog with the edges of `labels` only
+    /// and the ends of its definition or of its manifest entry, every vertex without them.
+    fn stored_graph(&mut self, name: &str, labels: Option<&HashSet<String>>) -> Result<Dfa> {
+        if let Some(expr) = self.graph_definitions.get(name).cloned() {
+            let dfa = self.computed_graph(&expr)?;
+            return Ok(Dfa {
+                graph: restricted(&dfa.graph, labels),
+                initials: dfa.initials.clone(),
+                finals: dfa.finals.clone(),
+            });
+        }
+        let graph = restricted(self.graph(name)?, labels);
+        let entry = self.catalog(&format!("graph '{}'", name))?.entry(name)?;
+        let ends = |ends: Option<&Vec<usize>>| match ends {
+            Some(ends) => ends.iter().cloned().collect(),
+            None => (0..graph.size).collect(),
+        };
+        let (initials, finals) = match entry {
+            Some(entry) => (ends(entry.initials.as_ref()), ends(entry.finals.as_ref())),
+            None => (ends(None), ends(None)),
+        };
+        Ok(Dfa {
+            graph,
+            initials,
+            finals,
+        })
+    }
+
+    /// Ends given for the graph `name` of `size` vertices, omitted ones are parsed as an empty `Set`
+    /// and stand for `default`. Vertices reachable by a pattern are the ones listed by
+    /// `get vertices from application of pattern to name with initials as from`.
+    fn vertices(
+        &mut self,
+        name: &str,
+        vertices: &Vertices,
+        default: HashSet<usize>,
+        size: usize,
+    ) -> Result<HashSet<usize>> {
+        Ok(match vertices {
+            Set(set) if set.is_empty() => default,
+            Set(set) => set.iter().cloned().collect(),
+            Range { from, to } => (*from..=*to).collect(),
+            EmptySet => HashSet::new(),
+            Reachable(from, pattern) => {
+                let graph = WithEnds {
+                    graph: name.to_string(),
+                    initials: from.as_ref().clone(),
+                    finals: Range {
+                        from: 0,
+                        to: size.saturating_sub(1),
+                    },
+                };
+                let source = Apply(pattern.clone(), Intersection(vec![graph]));
+                self.source(&source, None)?.vertices().into_iter().collect()
+            }
+            Of(source) => self.source(source, None)?.vertices().into_iter().collect(),
+        })
+    }
+
+    /// Value of a defined graph, computed on first use and kept until the graphs it is made of
+    /// may change: on `connect` to another database, `save graph` or a definition of one of them,
+    /// or of a pattern its ends are found by.
+    fn computed_graph(&mut self, expr: &GraphExpr) -> Result<&Dfa> {
+        if !self.computed.contains_key(expr) {
+            let dfa = self.graph_expr(expr, None)?;
+            self.computed.insert(expr.clone(), dfa);
+        }
+        Ok(&self.computed[expr])
+    }
+
+    /// Drops the computed graphs with ends found by a query that uses the pattern or macro `name`.
+    fn forget_queries(&mut self, name: &str) {
+        let (patterns, macros) = (&self.patterns, &self.macros);
+        let definitions = &self.graph_definitions;
+        let uses_name = |pattern: &Pattern| refers_to(pattern, name, patterns, macros);
+        self.computed
+            .retain(|expr, _| !queries(expr, &uses_name, definitions));
+    }
+
+    /// The connected catalog, `used` names what needs it in the error without one.
+    fn catalog(&mut self, used: &str) -> Result<&mut GraphCatalog> {
+        let database = self
+            .database
+            .as_ref()
+            .ok_or_else(|| Error::msg(format!("{} is used before connect", used)))?;
+        Ok(self
+            .catalogs
+            .get_mut(database)
+            .expect("a catalog is opened on connect"))
+    }
+
+    fn graph(&mut self, name: &str) -> Result<&Graph> {
+        self.catalog(&format!("graph '{}'", name))?.graph(name)
+    }
+}
+
+/// Graph of a `get` source along with the edges it selects.
+struct Selection {
+    dfa: Dfa,
+    /// Pairs found by a path query, `None` when the edges of the graph itself are selected.
+    pairs: Option<Vec<Ends>>,
+    limits: ExecutionLimits,
+}
+
+impl Selection {
+    fn edges(&mut self) -> Vec<Ends> {
+        match self.pairs.take() {
+            Some(pairs) => pairs,
+            None => self.dfa.graph.adjacency_matrix().extract_pairs(),
+        }
+    }
+
+    /// Pairs found by the path query, or pairs of ends connected by a non-empty path in the graph.
+    fn pairs(&mut self) -> Result<Vec<Ends>> {
+        Ok(match self.pairs.take() {
+            Some(pairs) => pairs,
+            None => {
+                let dfa = &self.dfa;
+                dfa.graph
+                    .reachable_pairs(&self.limits)?
+                    .into_iter()
+                    .filter(|(from, to)| dfa.initials.contains(from) && dfa.finals.contains(to))
+                    .collect()
+            }
+        })
+    }
+
+    /// Vertices reached by the path query, or all vertices of the graph.
+    fn vertices(&mut self) -> Vec<usize> {
+        match self.pairs.take() {
+            Some(pairs) => {
+                let vertices: HashSet<usize> = pairs.into_iter().map(|(_, to)| to).collect();
+                vertices.into_iter().collect()
+            }
+            None => (0..self.dfa.graph.size).collect(),
+        }
+    }
+
+    fn list(&mut self, list: &ListExpr) -> Result<Value> {
+        let conditions = list.conditions();
+        let regexes = LabelRegexes::new(&conditions)?;
+        match list.object() {
+            VertexList => {
+                let mut vertices = self.vertices();
+                vertices.retain(|v| {
+                    conditions
+                        .iter()
+                        .all(|c| c.satisfied_by_vertex(&self.dfa, *v))
+                });
+                vertices.sort();
+                Ok(Value::Vertices(vertices))
+            }
+            Labels => {
+                let mut labels = self.dfa.graph.alphabet();
+                labels.retain(|l| conditions.iter().all(|c| c.satisfied_by_label(&regexes, l)));
+                Ok(Value::Labels(labels))
+            }
+            object => {
+                let mut edges = match object {
+                    Pairs => self.pairs()?,
+                    _ => self.edges(),
+                };
+                edges.retain(|edge| {
+                    conditions
+                        .iter()
+                        .all(|c| c.satisfied(&self.dfa, &regexes, *edge))
+                });
+                edges.sort();
+                Ok(Value::Edges(edges))
+            }
+        }
+    }
+
+    fn count(&mut self, list: &ListExpr) -> Result<usize> {
+        if list.conditions().is_empty() && *list.object() == Edges {
+            return Ok(match &self.pairs {
+                Some(pairs) => pairs.len(),
+                None => self.dfa.graph.adjacency_matrix().nvals() as usize,
+            });
+        }
+
+        Ok(match self.list(list)? {
+            Value::Edges(edges) => edges.len(),
+            Value::Vertices(vertices) => vertices.len(),
+            Value::Labels(labels) => labels.len(),
+            _ => unreachable!(),
+        })
+    }
+
+    /// Matrix of the listed edges or pairs, the adjacency matrix itself when nothing is filtered out.
+    fn selected(&mut self, list: &ListExpr) -> Result<BooleanMatrix> {
+        if list.conditions().is_empty() && *list.object() == Edges && self.pairs.is_none() {
+            return Ok(self.dfa.graph.adjacency_matrix());
+        }
+        match self.list(list)? {
+            Value::Edges(edges) => Ok(self.dfa.graph.pairs_matrix(&edges)),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Numbers of the graph's listed edges by label, an edge with several labels is listed
+    /// once for each label, so label conditions apply to that label only.
+    /// Pairs and query results have no labels.
+    fn labels(&mut self, list: &ListExpr) -> Result<HashMap<String, usize>> {
+        if self.pairs.is_some() || *list.object() == Pairs {
+            return Err(Error::msg(
+                "only edges of a graph are grouped by label, pairs have no labels",
+            ));
+        }
+        let conditions = list.conditions();
+        let regexes = LabelRegexes::new(&conditions)?;
+        let dfa = &self.dfa;
+        Ok(dfa
+            .graph
+            .matrices
+            .iter()
+            .filter_map(|(label, matrix)| {
+                let selected = conditions.iter().fold(matrix.clone(), |selected, condition| {
+                    condition.select(dfa, &regexes, label, &selected)
+                });
+                match selected.nvals() as usize {
+                    0 => None,
+                    count => Some((label.clone(), count)),
+                }
+            })
+            .collect())
+    }
+
+    /// Out-degrees of the listed edges for `Begin`, in-degrees for `End`.
+    fn degrees(&mut self, list: &ListExpr, key: GroupKey) -> Result<Vec<(usize, usize)>> {
+        let selected = self.selected(list)?;
+        Ok(match key {
+            Begin => selected.out_degrees(),
+            _ => selected.in_degrees(),
+        })
+    }
+
+    fn count_by(&mut self, list: &ListExpr, key: GroupKey) -> Result<Value> {
+        Ok(match key {
+            Label => Value::counts(self.labels(list)?),
+            key => Value::VertexCounts(self.degrees(list, key)?.into_iter().collect()),
+        })
+    }
+
+    fn distinct(&mut self, key: GroupKey, list: &ListExpr) -> Result<Value> {
+        Ok(match key {
+            Label => {
+                let mut labels: Vec<String> = self.labels(list)?.into_keys().collect();
+                labels.sort();
+                Value::Labels(labels)
+            }
+            key => {
+                let mut vertices: Vec<usize> = self
+                    .degrees(list, key)?
+                    .into_iter()
+                    .map(|(v, _)| v)
+                    .collect();
+                vertices.sort();
+                Value::Vertices(vertices)
+            }
+        })
+    }
+}
+
+fn restricted(graph: &Graph, labels: Option<&HashSet<String>>) -> Graph {
+    match labels {
+        Some(labels) => graph.restricted(&labels.iter().collect::<Vec<_>>()),
+        None => graph.clone(),
+    }
+}
+
+/// Whether the expression uses the graph `name`, directly or through the graphs defined by `definitions`.
+fn uses(expr: &GraphExpr, name: &str, definitions: &HashMap<String, GraphExpr>) -> bool {
+    match expr {
+        Intersection(graphs) => graphs
+            .iter()
+            .any(|graph| simple_uses(graph, name, definitions)),
+        Union(e1, e2) | Difference(e1, e2) => {
+            uses(e1, name, definitions) || uses(e2, name, definitions)
+        }
+    }
+}
+
+fn simple_uses(graph: &SimpleGraph, name: &str, definitions: &HashMap<String, GraphExpr>) -> bool {
+    match graph {
+        GraphName(graph) | WithEnds { graph, .. } => {
+            graph == name
+                || definitions
+                    .get(graph)
+                    .is_some_and(|expr| uses(expr, name, definitions))
+        }
+        Reverse(graph) | Restricted(graph, _) => simple_uses(graph, name, definitions),
+        Nested(expr) => uses(expr, name, definitions),
+    }
+}
+
+/// Whether ends of the expression are found by a query with a pattern `queried` holds for,
+/// directly or through the graphs defined by `definitions`.
+fn queries(
+    expr: &GraphExpr,
+    queried: &dyn Fn(&Pattern) -> bool,
+    definitions: &HashMap<String, GraphExpr>,
+) -> bool {
+    match expr {
+        Intersection(graphs) => graphs
+            .iter()
+            .any(|graph| simple_queries(graph, queried, definitions)),
+        Union(e1, e2) | Difference(e1, e2) => {
+            queries(e1, queried, definitions) || queries(e2, queried, definitions)
+        }
+    }
+}
+
+fn simple_queries(
+    graph: &SimpleGraph,
+    queried: &dyn Fn(&Pattern) -> bool,
+    definitions: &HashMap<String, GraphExpr>,
+) -> bool {
+    let defined = |graph: &String| {
+        definitions
+            .get(graph)
+            .is_some_and(|expr| queries(expr, queried, definitions))
+    };
+    match graph {
+        GraphName(graph) => defined(graph),
+        WithEnds {
+            graph,
+            initials,
+            finals,
+        } => {
+            defined(graph)
+                || ends_queries(initials, queried, definitions)
+                || ends_queries(finals, queried, definitions)
+        }
+        Reverse(graph) | Restricted(graph, _) => simple_queries(graph, queried, definitions),
+        Nested(expr) => queries(expr, queried, definitions),
+    }
+}
+
+fn ends_queries(
+    vertices: &Vertices,
+    queried: &dyn Fn(&Pattern) -> bool,
+    definitions: &HashMap<String, GraphExpr>,
+) -> bool {
+    match vertices {
+        Reachable(from, pattern) => queried(pattern) || ends_queries(from, queried, definitions),
+        Of(source) => match source.as_ref() {
+            Apply(pattern, expr) => queried(pattern) || queries(expr, queried, definitions),
+            Direct(expr) => queries(expr, queried, definitions),
+        },
+        Set(_) | Range { .. } | EmptySet => false,
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Edges(edges) => {
+                for (from, to) in edges {
+                    writeln!(f, "{} {}", from, to)?;
+                }
+                Ok(())
+            }
+            Value::Count(count) => writeln!(f, "{}", count),
+            Value::Vertices(vertices) => {
+                for vertex in vertices {
+                    writeln!(f, "{}", vertex)?;
+                }
+                Ok(())
+            }
+            Value::Labels(labels) => {
+                for label in labels {
+                    writeln!(f, "{}", label)?;
+                }
+                Ok(())
+            }
+            Value::Pairs(pairs) => {
+                for (nonterminal, edges) in pairs {
+                    for (from, to) in edges {
+                        writeln!(f, "{} {} {}", nonterminal, from, to)?;
+                    }
+                }
+                Ok(())
+            }
+            Value::Counts(counts) => {
+                for (label, count) in counts {
+                    writeln!(f, "{} {}", label, count)?;
+                }
+                Ok(())
+            }
+            Value::VertexCounts(counts) => {
+                for (vertex, count) in counts {
+                    writeln!(f, "{} {}", vertex, count)?;
+                }
+                Ok(())
+            }
+            Value::Graphs(names) => {
+                for name in names {
+                    writeln!(f, "{}", name)?;
+                }
+                Ok(())
+            }
+            Value::Plan(steps) => {
+                for step in steps {
+                    writeln!(f, "{}", step)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::compute::limits::{Limit, LimitExceeded};
+    use crate::syntax::build_ast;
+    use crate::compute::string::literal;
+
+    use super::*;
+
+    fn run(text: &str) -> Result<Vec<Value>> {
+        let mut interpreter = Interpreter::new(env!("CARGO_MANIFEST_DIR"));
+        interpreter.run(&build_ast(text)?)
+    }
+
+    #[test]
+    fn direct() -> Result<()> {
+        let values = run("connect to test_data.graphs get edges from graph1")?;
+        assert_eq!(vec![Value::Edges(vec![(0, 0), (0, 1), (1, 1)])], values);
+        Ok(())
+    }
+
+    #[test]
+    fn count() -> Result<()> {
+        let values = run("connect to test_data.graphs get count of edges from graph2")?;
+        assert_eq!(vec![Value::Count(5)], values);
+        Ok(())
+    }
+
+    #[test]
+    fn apply() -> Result<()> {
+        let values = run(concat!(
+            "connect to test_data.graphs\n",
+            "define bs as \"b\"+\n",
+            "get edges from application of \"a\"* bs to graph1",
+        ))?;
+        assert_eq!(vec![Value::Edges(vec![(0, 1), (1, 1)])], values);
+        Ok(())
+    }
+
+    #[test]
+    fn with_ends() -> Result<()> {
+        let values = run(concat!(
+            "connect to test_data.graphs\n",
+            "get edges from application of \"b\" to graph1 with initials as [1]",
+        ))?;
+        assert_eq!(vec![Value::Edges(vec![(1, 1)])], values);
+        Ok(())
+    }
+
+    #[test]
+    fn undefined() {
+        assert!(run("connect to test_data.graphs get edges from application of x to graph1").is_err());
+        assert!(run("get edges from graph1").is_err());
+    }
+
+    #[test]
+    fn recursive() -> Result<()> {
+        let values = run(concat!(
+            "connect to test_data.graphs\n",
+            "define s as \"a\" t | epsilon\n",
+            "define t as s \"b\"\n",
+            "get edges from application of s to brackets with initials as [0, 1]",
+        ))?;
+        assert_eq!(vec![Value::Edges(vec![(0, 0), (0, 4), (1, 1), (1, 3)])], values);
+
+        let values = run(concat!(
+            "connect to test_data.graphs\n",
+            "define s as \"a\" s \"b\" | \"a\" \"b\"\n",
+            "get count of edges from application of s \"b\"? to brackets",
+        ))?;
+        assert_eq!(vec![Value::Count(3)], values);
+        Ok(())
+    }
+
+    #[test]
+    fn epsilon() -> Result<()> {
+        let values = run(concat!(
+            "connect to test_data.graphs\n",
+            "define s as \"a\" s | epsilon\n",
+            "get pairs from application of \"a\"* to graph2\n",
+            "get pairs from application of s to graph2\n",
+            "get vertices which satisfy vertex is initial\n",
+            "    from graph2 with initials as (vertices reachable from [0] by \"a\"*)",
+        ))?;
+        let pairs = vec![(0, 0), (0, 1), (0, 2), (1, 1), (1, 2), (2, 2), (3, 3), (4, 4)];
+        assert_eq!(
+            vec![
+                Value::Edges(pairs.clone()),
+                Value::Edges(pairs),
+                Value::Vertices(vec![0, 1, 2]),
+            ],
+            values
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn redefined_ends() -> Result<()> {
+        let values = run(concat!(
+            "connect to test_data.graphs\n",
+            "define p as \"a\"\n",
+            "define q as p\n",
+            "define g as graph graph2 with initials as (vertices reachable from [0] by q)\n",
+            "get vertices which satisfy vertex is initial from g\n",
+            "define p as \"a\" \"a\"\n",
+            "get vertices which satisfy vertex is initial from g\n",
+            "define q(r) as r\n",
+            "define q as p \"b\"\n",
+            "get vertices which satisfy vertex is initial from g",
+        ))?;
+        assert_eq!(
+            vec![
+                Value::Vertices(vec![1]),
+                Value::Vertices(vec![2]),
+                Value::Vertices(vec![3]),
+            ],
+            values
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn objects() -> Result<()> {
+        let values = run(concat!(
+            "connect to test_data.graphs\n",
+            "get vertices from graph2\n",
+            "get vertices which satisfy vertex is initial and not vertex is final\n",
+            "    from graph2 with initials as [0..2] and finals as [1]\n",
+            "get vertices from application of \"a\" \"a\" to brackets\n",
+            "get labels from graph2\n",
+            "get count of labels which satisfy not label is \"a\" from graph2\n",
+            "get pairs from brackets with initials as [2]\n",
+            "get pairs which satisfy end is final from application of \"a\"* \"b\" to brackets\n",
+            "    with finals as [3]",
+        ))?;
+        assert_eq!(
+            vec![
+                Value::Vertices(vec![0, 1, 2, 3, 4]),
+                Value::Vertices(vec![0, 2]),
+                Value::Vertices(vec![2]),
+                Value::Labels(vec!["a".to_string(), "b".to_string()]),
+                Value::Count(1),
+                Value::Edges(vec![(2, 3), (2, 4)]),
+                Value::Edges(vec![(0, 3), (1, 3), (2, 3)]),
+            ],
+            values
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn algebra() -> Result<()> {
+        let values = run(concat!(
+            "connect to test_data.graphs\n",
+            "get edges from graph1 | graph2\n",
+            "get edges from graph1 - graph1 restricted to labels [\"b\"]\n",
+            "get count of edges from graph2 - graph1\n",
+            "get edges from reverse graph1\n",
+            "get edges from application of \"b\" \"b\" to reverse graph2\n",
+            "get labels from graph2 restricted to labels [\"b\", \"c\"]",
+        ))?;
+        assert_eq!(
+            vec![
+                Value::Edges(vec![(0, 0), (0, 1), (1, 1), (1, 2), (1, 4), (2, 3), (3, 0)]),
+                Value::Edges(vec![(0, 0)]),
+                Value::Count(5),
+                Value::Edges(vec![(0, 0), (1, 0), (1, 1)]),
+                Value::Edges(vec![(0, 2)]),
+                Value::Labels(vec!["b".to_string()]),
+            ],
+            values
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn predicates() -> Result<()> {
+        let values = run(concat!(
+            "connect to test_data.graphs\n",
+            "get edges which satisfy begin = end or end in [3..4] from graph2 | graph1\n",
+            "get edges which satisfy begin >= 1 and begin < 3 and not label in [\"b\"] from graph2\n",
+            "get pairs which satisfy end != 0 and begin in [2, 3] from graph2\n",
+            "get labels which satisfy label matches \"[ab]\" and not label matches \"a\" from graph2\n",
+            "get count of edges which satisfy label matches \"a|c\" from graph1",
+        ))?;
+        assert_eq!(
+            vec![
+                Value::Edges(vec![(0, 0), (1, 1), (1, 4), (2, 3)]),
+                Value::Edges(vec![(1, 2)]),
+                Value::Edges(vec![(2, 1), (2, 2), (2, 3), (2, 4), (3, 1), (3, 2), (3, 3), (3, 4)]),
+                Value::Labels(vec!["b".to_string()]),
+                Value::Count(1),
+            ],
+            values
+        );
+        assert!(run(concat!(
+            "connect to test_data.graphs\n",
+            "get labels which satisfy label matches \"(\" from graph1",
+        ))
+        .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn repetition() -> Result<()> {
+        let values = run(concat!(
+            "connect to test_data.graphs\n",
+            "get pairs from application of .{2} to graph2\n",
+            "get pairs from application of ![\"b\"]{1,3} to graph2\n",
+            "get count of pairs from application of \"b\"{2,} to graph2",
+        ))?;
+        assert_eq!(
+            vec![
+                Value::Edges(vec![(0, 2), (0, 4), (1, 3), (2, 0), (3, 1)]),
+                Value::Edges(vec![(0, 1), (0, 2), (1, 2)]),
+                Value::Count(1),
+            ],
+            values
+        );
+        assert!(run(concat!(
+            "connect to test_data.graphs\n",
+            "get pairs from application of \"a\"{2,1} to graph2",
+        ))
+        .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn inverse_labels() -> Result<()> {
+        let values = run(concat!(
+            "connect to test_data.graphs\n",
+            "get pairs from application of \"a\" ^\"a\" to graph2\n",
+            "get pairs from application of \"b\"^-1 \"a\"^-1 to graph2\n",
+            "define s as \"a\" s ^\"b\" | \"b\"\n",
+            "get pairs from application of s to graph2\n",
+            "get labels from graph2",
+        ))?;
+        assert_eq!(
+            vec![
+                Value::Edges(vec![(0, 0), (1, 1)]),
+                Value::Edges(vec![(3, 1), (4, 0)]),
+                Value::Edges(vec![(0, 1), (1, 2), (1, 4), (2, 3), (3, 0)]),
+                Value::Labels(vec!["a".to_string(), "b".to_string()]),
+            ],
+            values
+        );
+
+        let values = run(concat!(
+            "connect to test_data.graphs\n",
+            "get pairs from application of ^\"a\" to inverse_names\n",
+            "get pairs from application of \"a^-1\" to inverse_names\n",
+            "get pairs from application of \"^a\" to inverse_names\n",
+            "get pairs from application of \"b^-1\" to graph2",
+        ))?;
+        assert_eq!(
+            vec![
+                Value::Edges(vec![(1, 0)]),
+                Value::Edges(vec![(1, 2)]),
+                Value::Edges(vec![(2, 0)]),
+                Value::Edges(Vec::new()),
+            ],
+            values
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn macros() -> Result<()> {
+        let values = run(concat!(
+            "connect to test_data.graphs\n",
+            "define within(p, q) as p* q\n",
+            "define s as within(\"a\", s) | \"b\"\n",
+            "get pairs from application of within(\"a\", \"b\") to graph2\n",
+            "get pairs from application of s to graph2",
+        ))?;
+        assert_eq!(
+            vec![
+                Value::Edges(vec![(0, 3), (0, 4), (1, 3), (1, 4), (2, 3), (3, 0)]),
+                Value::Edges(vec![(0, 3), (0, 4), (1, 3), (1, 4), (2, 3), (3, 0)]),
+            ],
+            values
+        );
+        assert!(run(concat!(
+            "connect to test_data.graphs\n",
+            "define within(p, q) as p* q\n",
+            "get pairs from application of within(\"a\") to graph2",
+        ))
+        .is_err());
+        // Only the definitions a query uses are expanded
+        let values = run(concat!(
+            "connect to test_data.graphs\n",
+            "define broken as missing(\"a\")\n",
+            "define b as \"b\"\n",
+            "get pairs from application of b to graph2\n",
+            "explain get pairs from application of b to graph2",
+        ))?;
+        assert_eq!(Value::Edges(vec![(1, 4), (2, 3), (3, 0)]), values[0]);
+        assert!(run(concat!(
+            "connect to test_data.graphs\n",
+            "define broken as missing(\"a\")\n",
+            "get pairs from application of broken to graph2",
+        ))
+        .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn save() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("flat-practice-save-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let file = |name: &str| literal(dir.join(name).to_str().unwrap());
+        let values = run(&format!(
+            concat!(
+                "connect to test_data.graphs\n",
+                "define ab as \"a\" \"b\"\n",
+                "save edges from graph2 with initials as [1] to {}\n",
+                "save count of edges from graph1 to {}\n",
+                "save graph graph2 - graph1 restricted to labels [\"a\"] to {}\n",
+                "save graph application of ab to brackets to {}",
+            ),
+            file("edges.csv"),
+            file("count.json"),
+            file("difference"),
+            file("ab"),
+        ))?;
+        assert!(values.is_empty());
+
+        let read = |name: &str| std::fs::read_to_string(dir.join(name));
+        assert_eq!(
+            "from,to\n0,1\n1,2\n1,4\n2,3\n3,0\n",
+            read("edges.csv")?
+        );
+        assert_eq!(
+            serde_json::json!([{ "count": 3 }]),
+            serde_json::from_str::<serde_json::Value>(&read("count.json")?)?
+        );
+        assert_eq!(
+            "0 a 1\n1 a 2\n1 b 4\n2 b 3\n3 b 0\n",
+            read("difference")?
+        );
+        assert_eq!("1 ab 3\n", read("ab")?);
+        let graph = Graph::read_from(dir.join("difference"))?;
+        assert_eq!(5, graph.adjacency_matrix().nvals());
+        assert_eq!(4, Graph::read_from(dir.join("ab"))?.size);
+
+        run(&format!(
+            concat!(
+                "connect to test_data.graphs\n",
+                "save graph application of \"a\" to brackets to {}\n",
+                "save graph application of \"a\"+ \"b\" to brackets to {}",
+            ),
+            file("a"),
+            file("a_b"),
+        ))?;
+        assert_eq!("0 a 1\n1 a 2\n", read("a")?);
+        let graph = Graph::read_from(dir.join("a_b"))?;
+        assert_eq!(vec!["\"a\"+ \"b\""], graph.alphabet());
+        assert_eq!(vec![(0, 3), (1, 3)], graph.get("\"a\"+ \"b\"").unwrap().extract_pairs());
+        assert_eq!(4, graph.size);
+        Ok(())
+    }
+
+    #[test]
+    fn quoted_labels() -> Result<()> {
+        let values = run(concat!(
+            "connect to test_data.graphs\n",
+            "get labels from quoted\n",
+            "get edges from application of \"has part\" \"say \\\"hi\\\"\" to quoted\n",
+            "get edges which satisfy label in [\"rdf:type\", \"часть\"] from quoted",
+        ))?;
+        assert_eq!(
+            vec![
+                Value::Labels(vec![
+                    "has part".to_string(),
+                    "rdf:type".to_string(),
+                    "say \"hi\"".to_string(),
+                    "часть".to_string(),
+                ]),
+                Value::Edges(vec![(1, 0)]),
+                Value::Edges(vec![(0, 1), (0, 2)]),
+            ],
+            values
+        );
+
+        let path = std::env::temp_dir().join(format!("flat-practice-quoted-{}", std::process::id()));
+        run(&format!(
+            "connect to test_data.graphs save graph quoted to {}",
+            literal(path.to_str().unwrap())
+        ))?;
+        assert_eq!(
+            "1 \"has part\" 2\n0 rdf:type 1\n2 \"say \\\"hi\\\"\" 0\n0 часть 2\n",
+            std::fs::read_to_string(&path)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn include() -> Result<()> {
+        let values = run(concat!(
+            "connect to test_data.graphs\n",
+            "include \"test_data/include/main.q\"\n",
+            "get edges from application of bs ab to brackets",
+        ))?;
+        assert_eq!(
+            vec![
+                Value::Count(3),
+                Value::Edges(vec![(1, 3)]),
+                Value::Edges(Vec::new()),
+            ],
+            values
+        );
+
+        let error = |file: &str| {
+            run(&format!(
+                "connect to test_data.graphs include \"test_data/include/{}\"",
+                file
+            ))
+            .unwrap_err()
+            .to_string()
+        };
+        assert!(error("cycle_a.q").contains("cycle_a.q includes itself"));
+        assert!(error("lib/invalid.q").contains("invalid.q:2:4: unexpected end of input"));
+        assert!(error("lib/undefined.q").contains("undefined.q: "));
+        assert!(error("missing.q").starts_with("cannot include"));
+        Ok(())
+    }
+
+    #[test]
+    fn filters() -> Result<()> {
+        let values = run(concat!(
+            "connect to test_data.graphs\n",
+            "get edges which satisfy label is \"b\" from graph2\n",
+            "get edges which satisfy (begin is initial or end is final) and not label is \"a\"\n",
+            "    from graph2 with initials as [1] and finals as [0..2]\n",
+            "get edges which satisfy label is \"b\" which satisfy begin is initial\n",
+            "    from graph2 with initials as [1..2]\n",
+            "get count of edges which satisfy end is final from graph2 with finals as []\n",
+            "get count of edges which satisfy label is \"a\" from application of \"a\" | \"b\" \"b\" to graph2",
+        ))?;
+        assert_eq!(
+            vec![
+                Value::Edges(vec![(1, 4), (2, 3), (3, 0)]),
+                Value::Edges(vec![(1, 4), (3, 0)]),
+                Value::Edges(vec![(1, 4), (2, 3)]),
+                Value::Count(0),
+                Value::Count(2),
+            ],
+            values
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn groups() -> Result<()> {
+        let counts =
+            |counts: &[(usize, usize)]| Value::VertexCounts(counts.iter().cloned().collect());
+        let mut labels = BTreeMap::new();
+        labels.insert("a".to_string(), 2);
+        labels.insert("b".to_string(), 3);
+        let values = run(concat!(
+            "connect to test_data.graphs\n",
+            "get count of edges group by label from graph2\n",
+            "get count of edges which satisfy end != 0 group by begin from graph2\n",
+            "get count of edges group by end from graph2\n",
+            "get count of pairs group by begin from application of \"a\"+ to graph2\n",
+            "get distinct begins of edges which satisfy label is \"b\" from graph2\n",
+            "get distinct labels of edges which satisfy begin = 1 from graph2\n",
+            "get distinct ends of pairs from application of \"a\"+ to graph2",
+        ))?;
+        assert_eq!(
+            vec![
+                Value::Counts(labels),
+                counts(&[(0, 1), (1, 2), (2, 1)]),
+                counts(&[(0, 1), (1, 1), (2, 1), (3, 1), (4, 1)]),
+                counts(&[(0, 2), (1, 1)]),
+                Value::Vertices(vec![1, 2, 3]),
+                Value::Labels(vec!["a".to_string(), "b".to_string()]),
+                Value::Vertices(vec![1, 2]),
+            ],
+            values
+        );
+        assert!(run(concat!(
+            "connect to test_data.graphs\n",
+            "get count of edges group by label from application of \"a\" to graph2",
+        ))
+        .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn label_groups() -> Result<()> {
+        let counts = |counts: &[(&str, usize)]| {
+            Value::counts(counts.iter().map(|(l, c)| (l.to_string(), *c)).collect())
+        };
+        // (0, 1) is labeled both `a` and `b`
+        let values = run(concat!(
+            "connect to test_data.graphs\n",
+            "get count of edges which satisfy label is \"b\" group by label from graph1 | graph2\n",
+            "get count of edges which satisfy not label is \"a\" and end = 1 group by label\n",
+            "    from graph1 | graph2\n",
+            "get count of edges which satisfy label is \"b\" or begin = end group by label\n",
+            "    from graph1 | graph2\n",
+            "get distinct labels of edges which satisfy label is \"b\" and begin = 0 from graph1 | graph2",
+        ))?;
+        assert_eq!(
+            vec![
+                counts(&[("b", 5)]),
+                counts(&[("b", 2)]),
+                counts(&[("a", 1), ("b", 5)]),
+                Value::Labels(vec!["b".to_string()]),
+            ],
+            values
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn optimised() -> Result<()> {
+        let dfa = |name: &str| -> Result<Dfa> {
+            let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("test_data/graphs");
+            let graph = Graph::read_from(path.join(name))?;
+            let all: HashSet<usize> = (0..graph.size).collect();
+            Ok(Dfa {
+                graph,
+                initials: all.clone(),
+                finals: all,
+            })
+        };
+        let (graph1, graph2) = (dfa("graph1")?, dfa("graph2")?);
+        let limits = ExecutionLimits::default();
+        let mut intersection = graph2
+            .intersection(&graph1, &limits)?
+            .intersection(&graph2, &limits)?
+            .graph
+            .adjacency_matrix()
+            .extract_pairs();
+        intersection.sort();
+
+        let values = run(concat!(
+            "connect to test_data.graphs\n",
+            "get edges from graph2 & graph1 & graph2\n",
+            "get edges which satisfy label is \"a\" and not label is \"b\" from graph1\n",
+            "get edges which satisfy label is \"b\" from application of \"a\" | \"b\" to graph2\n",
+            "get labels from application of \"a\" to graph2",
+        ))?;
+        assert_eq!(
+            vec![
+                Value::Edges(intersection),
+                Value::Edges(vec![(0, 0)]),
+                Value::Edges(vec![(1, 4), (2, 3), (3, 0)]),
+                Value::Labels(vec!["a".to_string(), "b".to_string()]),
+            ],
+            values
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn explain() -> Result<()> {
+        let plan = |steps: &[&str]| Value::Plan(steps.iter().map(|s| s.to_string()).collect());
+        let values = run(concat!(
+            "connect to test_data.graphs\n",
+            "explain get count of edges from application of \"a\" \"b\" to graph2 & graph1\n",
+            "explain get pairs from graph1 | reverse graph2",
+        ))?;
+        assert_eq!(
+            vec![
+                plan(&[
+                    "labels kept: [\"a\", \"b\"]",
+                    "intersection in the order graph1 & graph2: 10 vertices, 8 edges",
+                    "  graph graph1 restricted to the kept labels: 2 vertices, 3 edges",
+                    "  graph graph2 restricted to the kept labels: 5 vertices, 5 edges",
+                    "regular path query: minimal DFA of 3 states and 2 edges",
+                    "kronecker product of the graph and the DFA: 10 * 3 = 30 vertices, 8 edges",
+                    "closure: repeated squaring of the 30 x 30 adjacency matrix",
+                    "result: listed without conditions",
+                ]),
+                plan(&[
+                    "labels kept: all",
+                    "union: 5 vertices, 8 edges",
+                    "  graph graph1: 2 vertices, 3 edges",
+                    "  reverse: 5 vertices, 5 edges",
+                    "    graph graph2: 5 vertices, 5 edges",
+                    "closure: repeated squaring of the 5 x 5 adjacency matrix",
+                    "result: listed without conditions",
+                ]),
+            ],
+            values
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn catalog() -> Result<()> {
+        let values = run(concat!(
+            "connect to test_data.catalog\n",
+            "list graphs\n",
+            "get pairs from application of \"knows\"+ to people\n",
+            "get pairs from application of \"knows\"+ to people with initials as [1]\n",
+            "get count of edges from chain\n",
+            "get labels from people",
+        ))?;
+        assert_eq!(
+            vec![
+                Value::Graphs(
+                    vec!["chain", "clash", "padded", "people", "swapped", "truncated"]
+                        .into_iter()
+                        .map(String::from)
+                        .collect()
+                ),
+                Value::Edges(vec![(0, 2)]),
+                Value::Edges(vec![(1, 2)]),
+                Value::Count(5),
+                Value::Labels(vec!["knows".to_string(), "likes, a lot".to_string()]),
+            ],
+            values
+        );
+        assert!(run("connect to test_data.catalog get edges from graph1").is_err());
+        assert!(run("list graphs").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn graph_definitions() -> Result<()> {
+        let values = run(concat!(
+            "connect to test_data.graphs\n",
+            "define both as graph graph1 & graph1\n",
+            "define start as graph graph1 with initials as [1]\n",
+            "get count of edges from both\n",
+            "get count of edges which satisfy label is \"b\" from both | graph2\n",
+            "explain get count of edges from both\n",
+            "get pairs from start\n",
+            "get pairs from start with finals as [0]\n",
+            "define loops as graph reverse start\n",
+            "get edges from loops\n",
+            "define start as graph graph2\n",
+            "get edges from loops",
+        ))?;
+        assert_eq!(
+            vec![
+                Value::Count(5),
+                Value::Count(6),
+                Value::Plan(vec![
+                    "labels kept: all".to_string(),
+                    "defined graph both: 4 vertices, 5 edges".to_string(),
+                    "result: listed without conditions".to_string(),
+                ]),
+                Value::Edges(vec![(1, 1)]),
+                Value::Edges(Vec::new()),
+                Value::Edges(vec![(0, 0), (1, 0), (1, 1)]),
+                Value::Edges(vec![(0, 3), (1, 0), (2, 1), (3, 2), (4, 1)]),
+            ],
+            values
+        );
+        assert!(run(concat!(
+            "connect to test_data.graphs\n",
+            "define graph1 as graph graph1 restricted to labels [\"a\"]",
+        ))
+        .is_err());
+        assert!(run(concat!(
+            "connect to test_data.graphs\n",
+            "define a as graph graph1\n",
+            "define b as graph reverse a\n",
+            "define a as graph b",
+        ))
+        .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn vertex_queries() -> Result<()> {
+        let values = run(concat!(
+            "connect to test_data.graphs\n",
+            "get edges from application of \"b\" to graph2\n",
+            "    with initials as (vertices reachable from [0] by \"a\"*)\n",
+            "get vertices which satisfy vertex is initial\n",
+            "    from graph2 with initials as (vertices of application of \"a\" to graph2 with initials as [0])\n",
+            "get vertices which satisfy vertex is final from graph2\n",
+            "    with finals as (vertices reachable from (vertices reachable from [0] by \"a\") by \"b\")\n",
+            "explain get edges from graph2 with initials as (vertices reachable from [0] by \"a\"*)",
+        ))?;
+        assert_eq!(
+            vec![
+                Value::Edges(vec![(1, 4), (2, 3)]),
+                Value::Vertices(vec![1]),
+                Value::Vertices(vec![4]),
+                Value::Plan(vec![
+                    "labels kept: all".to_string(),
+                    "graph graph2: 5 vertices, 5 edges".to_string(),
+                    "  initials found by the query (vertices reachable from [0] by \"a\"*)"
+                        .to_string(),
+                    "result: listed without conditions".to_string(),
+                ]),
+            ],
+            values
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn limits() -> Result<()> {
+        let mut interpreter = Interpreter::new(env!("CARGO_MANIFEST_DIR"));
+        interpreter.set_limits(ExecutionLimits {
+            max_iterations: Some(0),
+            ..ExecutionLimits::default()
+        });
+        interpreter.run(&build_ast("connect to test_data.graphs")?)?;
+        let error = interpreter
+            .run(&build_ast("get pairs from application of \"a\"* to graph2")?)
+            .unwrap_err();
+        let exceeded = error.downcast_ref::<LimitExceeded>().unwrap();
+        assert_eq!(Limit::Iterations(0), exceeded.limit);
+
+        let values = interpreter.run(&build_ast("get count of edges from graph2")?)?;
+        assert_eq!(vec![Value::Count(5)], values);
+        Ok(())
+    }
+}
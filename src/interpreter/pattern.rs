@@ -0,0 +1,786 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+
+use anyhow::Result;
+
+use crate::compute::dfa::Dfa;
+use crate::compute::graph::{inverse, label_key, Edge, Graph};
+use crate::compute::regex::{AutomatonEdges, EpsilonNfa};
+use crate::compute::rfa::Rfa;
+use crate::syntax::ast::*;
+
+/// Nonterminal of the RFA box built for the queried pattern itself.
+pub(crate) const QUERY_NONTERMINAL: &str = "<>";
+
+/// Label of the RFA transitions that stand for a call of a recursive definition,
+/// `<` keeps it apart from the keys of labels (see `label_key`).
+pub(crate) fn nonterminal(name: &str) -> String {
+    format!("<{}>", name)
+}
+
+/// Largest count of a repetition `p{n,m}`.
+pub const MAX_REPETITION: usize = 1000;
+
+/// Most automaton states the copies of repeated patterns may take, counting the copies
+/// of every enclosing repetition: `("a"{1000}){1000}` would take two million.
+pub const MAX_REPEATED_STATES: usize = 10_000;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum PatternError {
+    UndefinedVariable(String),
+    RecursiveDefinition(String),
+    /// `p{n,m}` with `m < n`.
+    DescendingRepetition(usize, usize),
+    /// `p{n}` with `n` above `MAX_REPETITION` or with copies of `p` above `MAX_REPEATED_STATES`,
+    /// the automaton has a copy of `p` for each repetition.
+    LongRepetition(usize),
+    UndefinedMacro(String),
+    /// A parameterised definition called with a wrong number of arguments: expected, given.
+    WrongArity(String, usize, usize),
+}
+
+impl Display for PatternError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PatternError::UndefinedVariable(name) => write!(f, "undefined variable '{}'", name),
+            PatternError::RecursiveDefinition(name) => {
+                write!(f, "recursive definition of '{}'", name)
+            }
+            PatternError::DescendingRepetition(from, to) => {
+                write!(
+                    f,
+                    "repetition {{{},{}}} is empty, {} > {}",
+                    from, to, from, to
+                )
+            }
+            PatternError::LongRepetition(n) => write!(
+                f,
+                "repetition {} is too long, at most {} repetitions and {} automaton states are allowed, use * or + instead",
+                n, MAX_REPETITION, MAX_REPEATED_STATES
+            ),
+            PatternError::UndefinedMacro(name) => {
+                write!(f, "undefined parameterised definition '{}'", name)
+            }
+            PatternError::WrongArity(name, expected, given) => write!(
+                f,
+                "'{}' takes {} argument(s), {} given",
+                name, expected, given
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PatternError {}
+
+/// Parameterised definitions, their parameters and bodies by name.
+pub type Macros = HashMap<String, (Vec<String>, Pattern)>;
+
+struct MacroExpander<'a> {
+    macros: &'a Macros,
+    expanding: Vec<&'a str>,
+}
+
+impl<'a> MacroExpander<'a> {
+    /// Replaces the calls in `pattern` with the bodies they refer to, `arguments` are the values
+    /// of the parameters of the body `pattern` comes from. Arguments are expanded in the scope
+    /// of the call, so neither they nor the body can capture each other's names.
+    fn expand(
+        &mut self,
+        pattern: &Pattern,
+        arguments: &HashMap<&str, Pattern>,
+    ) -> Result<Pattern, PatternError> {
+        Ok(match pattern {
+            Var(name) => match (arguments.get(name.as_str()), self.macros.get(name)) {
+                (Some(argument), _) => argument.clone(),
+                (None, Some((parameters, _))) => {
+                    return Err(PatternError::WrongArity(name.clone(), parameters.len(), 0))
+                }
+                (None, None) => Var(name.clone()),
+            },
+            Star(p) => Star(Box::new(self.expand(p, arguments)?)),
+            Plus(p) => Plus(Box::new(self.expand(p, arguments)?)),
+            Maybe(p) => Maybe(Box::new(self.expand(p, arguments)?)),
+            Repeat(p, from, to) => Repeat(Box::new(self.expand(p, arguments)?), *from, *to),
+            Alt(p1, p2) => Alt(
+                Box::new(self.expand(p1, arguments)?),
+                Box::new(self.expand(p2, arguments)?),
+            ),
+            Seq(ps) => Seq(ps
+                .iter()
+                .map(|p| self.expand(p, arguments))
+                .collect::<Result<_, _>>()?),
+            Call(name, given) => {
+                let (name, (parameters, body)) = self
+                    .macros
+                    .get_key_value(name)
+                    .ok_or_else(|| PatternError::UndefinedMacro(name.clone()))?;
+                if parameters.len() != given.len() {
+                    return Err(PatternError::WrongArity(
+                        name.clone(),
+                        parameters.len(),
+                        given.len(),
+                    ));
+                }
+                if self.expanding.contains(&name.as_str()) {
+                    return Err(PatternError::RecursiveDefinition(name.clone()));
+                }
+                let mut values = HashMap::new();
+                for (parameter, argument) in parameters.iter().zip(given) {
+                    values.insert(parameter.as_str(), self.expand(argument, arguments)?);
+                }
+                self.expanding.push(name);
+                let expanded = self.expand(body, &values)?;
+                self.expanding.pop();
+                expanded
+            }
+            Term(_) | Inverse(_) | AnyLabel | NotLabels(_) => pattern.clone(),
+        })
+    }
+}
+
+/// The pattern without calls of parameterised definitions.
+/// A definition that calls itself, directly or through other ones, can not be expanded.
+pub fn expand(pattern: &Pattern, macros: &Macros) -> Result<Pattern> {
+    let mut expander = MacroExpander {
+        macros,
+        expanding: Vec::new(),
+    };
+    Ok(expander.expand(pattern, &HashMap::new())?)
+}
+
+/// `expand` applied to the definitions reachable from the expanded `pattern`,
+/// so a definition the pattern does not use can not make it fail.
+pub fn expand_definitions(
+    pattern: &Pattern,
+    definitions: &HashMap<String, Pattern>,
+    macros: &Macros,
+) -> Result<HashMap<String, Pattern>> {
+    let mut expanded = HashMap::new();
+    let mut vars = Vec::new();
+    variables(pattern, &mut vars);
+    let mut stack: Vec<String> = vars.into_iter().map(String::from).collect();
+    while let Some(name) = stack.pop() {
+        if expanded.contains_key(&name) {
+            continue;
+        }
+        if let Some(definition) = definitions.get(&name) {
+            let definition = expand(definition, macros)?;
+            let mut vars = Vec::new();
+            variables(&definition, &mut vars);
+            stack.extend(vars.into_iter().map(String::from));
+            expanded.insert(name, definition);
+        }
+    }
+    Ok(expanded)
+}
+
+struct PatternCompiler<'a> {
+    definitions: &'a HashMap<String, Pattern>,
+    nonterminals: &'a HashSet<String>,
+    /// Labels of the queried graph, what `.` and `![...]` are expanded to.
+    alphabet: &'a [String],
+    nfa: EpsilonNfa,
+    visiting: Vec<&'a str>,
+    /// Copies made of the current pattern by the enclosing repetitions.
+    copies: usize,
+}
+
+impl<'a> PatternCompiler<'a> {
+    fn fragment(&mut self, pattern: &'a Pattern) -> Result<(usize, usize), PatternError> {
+        Ok(match pattern {
+            Term(label) => {
+                let (from, to) = (self.nfa.add_state(), self.nfa.add_state());
+                self.nfa.add_edge(from, to, Some(label_key(label).into_owned()));
+                (from, to)
+            }
+            Inverse(label) => {
+                let (from, to) = (self.nfa.add_state(), self.nfa.add_state());
+                self.nfa.add_edge(from, to, Some(inverse(label)));
+                (from, to)
+            }
+            Var(name) if self.nonterminals.contains(name) => {
+                let (from, to) = (self.nfa.add_state(), self.nfa.add_state());
+                self.nfa.add_edge(from, to, Some(nonterminal(name)));
+                (from, to)
+            }
+            Var(name) => match self.definitions.get(name) {
+                Some(_) if self.visiting.contains(&name.as_str()) => {
+                    return Err(PatternError::RecursiveDefinition(name.clone()))
+                }
+                Some(definition) => {
+                    self.visiting.push(name);
+                    let fragment = self.fragment(definition)?;
+                    self.visiting.pop();
+                    fragment
+                }
+                None if name == "epsilon" => {
+                    let state = self.nfa.add_state();
+                    (state, state)
+                }
+                None => return Err(PatternError::UndefinedVariable(name.clone())),
+            },
+            Star(p) => {
+                let state = self.nfa.add_state();
+                let (from, to) = self.fragment(p)?;
+                self.nfa.add_edge(state, from, None);
+                self.nfa.add_edge(to, state, None);
+                (state, state)
+            }
+            Plus(p) => {
+                let (from, to) = self.fragment(p)?;
+                let last = self.nfa.add_state();
+                self.nfa.add_edge(to, from, None);
+                self.nfa.add_edge(to, last, None);
+                (from, last)
+            }
+            Maybe(p) => {
+                let (from, to) = self.fragment(p)?;
+                let (first, last) = (self.nfa.add_state(), self.nfa.add_state());
+                self.nfa.add_edge(first, from, None);
+                self.nfa.add_edge(to, last, None);
+                self.nfa.add_edge(first, last, None);
+                (first, last)
+            }
+            Repeat(p, from, to) => {
+                if let Some(to) = to {
+                    if to < from {
+                        return Err(PatternError::DescendingRepetition(*from, *to));
+                    }
+                }
+                let longest = to.unwrap_or(*from);
+                if longest > MAX_REPETITION {
+                    return Err(PatternError::LongRepetition(longest));
+                }
+                let enclosing = self.copies;
+                self.copies = enclosing.saturating_mul(to.unwrap_or(*from + 1));
+                let first = self.nfa.add_state();
+                let mut last = first;
+                for _ in 0..*from {
+                    let (from, to) = self.copy(p, longest)?;
+                    self.nfa.add_edge(last, from, None);
+                    last = to;
+                }
+                let fragment = match to {
+                    Some(to) => {
+                        let end = self.nfa.add_state();
+                        for _ in *from..*to {
+                            self.nfa.add_edge(last, end, None);
+                            let (from, to) = self.copy(p, longest)?;
+                            self.nfa.add_edge(last, from, None);
+                            last = to;
+                        }
+                        self.nfa.add_edge(last, end, None);
+                        (first, end)
+                    }
+                    None => {
+                        let state = self.nfa.add_state();
+                        let (from, to) = self.copy(p, longest)?;
+                        self.nfa.add_edge(last, state, None);
+                        self.nfa.add_edge(state, from, None);
+                        self.nfa.add_edge(to, state, None);
+                        (first, state)
+                    }
+                };
+                self.copies = enclosing;
+                fragment
+            }
+            AnyLabel => self.labels(|_| true),
+            NotLabels(labels) => self.labels(|label| !labels.contains(label)),
+            Alt(p1, p2) => {
+                let (first, last) = (self.nfa.add_state(), self.nfa.add_state());
+                for p in &[p1, p2] {
+                    let (from, to) = self.fragment(p)?;
+                    self.nfa.add_edge(first, from, None);
+                    self.nfa.add_edge(to, last, None);
+                }
+                (first, last)
+            }
+            Seq(ps) => {
+                let first = self.nfa.add_state();
+                let mut last = first;
+                for p in ps {
+                    let (from, to) = self.fragment(p)?;
+                    self.nfa.add_edge(last, from, None);
+                    last = to;
+                }
+                (first, last)
+            }
+            Call(name, _) => return Err(PatternError::UndefinedMacro(name.clone())),
+        })
+    }
+
+    /// A copy of the repeated pattern `p`, failing when all the copies of it would take
+    /// more than `MAX_REPEATED_STATES` states.
+    fn copy(&mut self, p: &'a Pattern, count: usize) -> Result<(usize, usize), PatternError> {
+        let states = self.nfa.size();
+        let fragment = self.fragment(p)?;
+        if (self.nfa.size() - states).saturating_mul(self.copies) > MAX_REPEATED_STATES {
+            return Err(PatternError::LongRepetition(count));
+        }
+        Ok(fragment)
+    }
+
+    /// A transition on every label of the alphabet that `accept` holds for.
+    fn labels<F: Fn(&String) -> bool>(&mut self, accept: F) -> (usize, usize) {
+        let (from, to) = (self.nfa.add_state(), self.nfa.add_state());
+        for label in self.alphabet.iter().filter(|l| accept(l)) {
+            self.nfa.add_edge(from, to, Some(label_key(label).into_owned()));
+        }
+        (from, to)
+    }
+}
+
+/// Compiles a pattern into a minimal automaton, inlining every variable except `nonterminals`,
+/// which become transitions labeled with `nonterminal(name)`. Labels are matched by their keys,
+/// see `Graph::with_inverses`. An undefined `epsilon` variable stands for the empty word.
+fn compile(
+    pattern: &Pattern,
+    definitions: &HashMap<String, Pattern>,
+    nonterminals: &HashSet<String>,
+    alphabet: &[String],
+) -> Result<AutomatonEdges> {
+    let mut compiler = PatternCompiler {
+        definitions,
+        nonterminals,
+        alphabet,
+        nfa: EpsilonNfa::new(),
+        visiting: Vec::new(),
+        copies: 1,
+    };
+    let (from, to) = compiler.fragment(pattern)?;
+    Ok(compiler.nfa.to_minimal_dfa(from, &[to]))
+}
+
+fn variables<'a>(pattern: &'a Pattern, vars: &mut Vec<&'a str>) {
+    match pattern {
+        Term(_) | Inverse(_) => {}
+        Var(name) => vars.push(name),
+        Star(p) | Plus(p) | Maybe(p) | Repeat(p, ..) => variables(p, vars),
+        AnyLabel | NotLabels(_) => {}
+        Alt(p1, p2) => {
+            variables(p1, vars);
+            variables(p2, vars);
+        }
+        Seq(ps) => ps.iter().for_each(|p| variables(p, vars)),
+        Call(_, ps) => ps.iter().for_each(|p| variables(p, vars)),
+    }
+}
+
+/// Labels of the graph `pattern` and the definitions it refers to can match,
+/// traversed forwards or backwards. `None` if a wildcard can match any label.
+pub fn pattern_labels(
+    pattern: &Pattern,
+    definitions: &HashMap<String, Pattern>,
+) -> Option<HashSet<String>> {
+    let mut labels = HashSet::new();
+    let mut visited = HashSet::<&str>::new();
+    let mut stack = vec![pattern];
+    while let Some(pattern) = stack.pop() {
+        match pattern {
+            Term(label) | Inverse(label) => {
+                labels.insert(label.clone());
+            }
+            AnyLabel | NotLabels(_) => return None,
+            Var(name) => {
+                if visited.insert(name.as_str()) {
+                    stack.extend(definitions.get(name));
+                }
+            }
+            Star(p) | Plus(p) | Maybe(p) | Repeat(p, ..) => stack.push(p),
+            Alt(p1, p2) => {
+                stack.push(p1);
+                stack.push(p2);
+            }
+            Seq(ps) | Call(_, ps) => stack.extend(ps.iter()),
+        }
+    }
+    Some(labels)
+}
+
+/// Whether `pattern` uses the definition or the macro `name`, directly or through other ones.
+pub fn refers_to(
+    pattern: &Pattern,
+    name: &str,
+    definitions: &HashMap<String, Pattern>,
+    macros: &Macros,
+) -> bool {
+    let mut visited = HashSet::<&str>::new();
+    let mut stack = vec![pattern];
+    while let Some(pattern) = stack.pop() {
+        match pattern {
+            Var(used) | Call(used, _) if used == name => return true,
+            Var(used) | Call(used, _) if visited.insert(used.as_str()) => {
+                stack.extend(definitions.get(used));
+                stack.extend(macros.get(used).map(|(_, body)| body));
+            }
+            _ => {}
+        }
+        match pattern {
+            Star(p) | Plus(p) | Maybe(p) | Repeat(p, ..) => stack.push(p),
+            Alt(p1, p2) => {
+                stack.push(p1);
+                stack.push(p2);
+            }
+            Seq(ps) | Call(_, ps) => stack.extend(ps.iter()),
+            Term(_) | Inverse(_) | AnyLabel | NotLabels(_) | Var(_) => {}
+        }
+    }
+    false
+}
+
+/// Definitions reachable from `pattern` that refer to themselves, directly or through other ones.
+pub fn recursive_definitions(
+    pattern: &Pattern,
+    definitions: &HashMap<String, Pattern>,
+) -> Result<HashSet<String>> {
+    let mut dependencies = HashMap::<&str, Vec<&str>>::new();
+    let mut stack = Vec::new();
+    variables(pattern, &mut stack);
+    while let Some(name) = stack.pop() {
+        if dependencies.contains_key(name) {
+            continue;
+        }
+        match definitions.get(name) {
+            Some(definition) => {
+                let mut vars = Vec::new();
+                variables(definition, &mut vars);
+                stack.extend(vars.iter());
+                dependencies.insert(name, vars);
+            }
+            None if name == "epsilon" => {}
+            None => Err(PatternError::UndefinedVariable(name.to_string()))?,
+        }
+    }
+
+    let mut recursive = HashSet::new();
+    for name in dependencies.keys() {
+        let mut visited = HashSet::<&str>::new();
+        let mut stack = dependencies[name].clone();
+        while let Some(next) = stack.pop() {
+            if next == *name {
+                recursive.insert(name.to_string());
+                break;
+            }
+            if visited.insert(next) {
+                if let Some(vars) = dependencies.get(next) {
+                    stack.extend(vars.iter());
+                }
+            }
+        }
+    }
+    Ok(recursive)
+}
+
+impl Dfa {
+    /// Compiles a pattern without recursive definitions into a minimal automaton,
+    /// `alphabet` is the labels of the graph it is going to be applied to.
+    pub fn from_pattern(
+        pattern: &Pattern,
+        definitions: &HashMap<String, Pattern>,
+        alphabet: &[String],
+    ) -> Result<Dfa> {
+        let (initial, finals, edges) = compile(pattern, definitions, &HashSet::new(), alphabet)?;
+
+        Ok(Dfa {
+            graph: Graph::build(edges.as_slice()),
+            initials: [initial].iter().cloned().collect(),
+            finals: finals.into_iter().collect(),
+        })
+    }
+}
+
+impl Rfa {
+    /// Builds one box per recursive definition and one for the pattern itself,
+    /// named `QUERY_NONTERMINAL`. Non-recursive definitions are inlined into the boxes.
+    pub fn from_pattern(
+        pattern: &Pattern,
+        definitions: &HashMap<String, Pattern>,
+        recursive: &HashSet<String>,
+        alphabet: &[String],
+    ) -> Result<Rfa> {
+        let mut boxes: Vec<(String, &Pattern)> = recursive
+            .iter()
+            .map(|name| (nonterminal(name), &definitions[name]))
+            .collect();
+        boxes.sort_by(|(n1, _), (n2, _)| n1.cmp(n2));
+        boxes.push((QUERY_NONTERMINAL.to_string(), pattern));
+
+        let mut edges = Vec::<Edge>::new();
+        let mut initials = HashSet::<usize>::new();
+        let mut finals = HashSet::<usize>::new();
+        let mut with_epsilon = HashSet::<String>::new();
+        let mut nonterminals = HashSet::<String>::new();
+        let mut ends2nonterminal = HashMap::<(usize, usize), String>::new();
+
+        let mut size: usize = 0;
+
+        for (name, pattern) in boxes {
+            let (box_initial, box_finals, box_edges) =
+                compile(pattern, definitions, recursive, alphabet)?;
+
+            let mut max = box_initial;
+            initials.insert(size + box_initial);
+            for box_final in box_finals {
+                if box_final == box_initial {
+                    with_epsilon.insert(name.clone());
+                }
+                ends2nonterminal.insert((size + box_initial, size + box_final), name.clone());
+                finals.insert(size + box_final);
+                max = max.max(box_final);
+            }
+
+            for (from, to, label) in box_edges {
+                edges.push((size + from, size + to, label));
+                max = max.max(from).max(to);
+            }
+
+            nonterminals.insert(name);
+            size += max + 1;
+        }
+
+        Ok(Rfa {
+            dfa: Dfa {
+                graph: Graph::build(edges.as_slice()),
+                initials,
+                finals,
+            },
+            nonterminals,
+            ends2nonterminal,
+            with_epsilon,
+            initial: QUERY_NONTERMINAL.to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+
+    use anyhow::Result;
+
+    use crate::compute::dfa::Dfa;
+    use crate::compute::graph::{inverse, label_key};
+    use crate::syntax::ast::*;
+    use crate::syntax::parse_statement;
+
+    use super::{
+        expand, nonterminal, pattern_labels, recursive_definitions, Macros, PatternError,
+    };
+
+    fn definitions(lines: &[&str]) -> Result<HashMap<String, Pattern>> {
+        let mut map = HashMap::new();
+        for line in lines {
+            if let Define(name, pattern) = parse_statement(line)? {
+                map.insert(name, pattern);
+            }
+        }
+        Ok(map)
+    }
+
+    fn macros(lines: &[&str]) -> Result<Macros> {
+        let mut map = HashMap::new();
+        for line in lines {
+            if let Macro(name, parameters, pattern) = parse_statement(line)? {
+                map.insert(name, (parameters, pattern));
+            }
+        }
+        Ok(map)
+    }
+
+    fn pattern(text: &str) -> Result<Pattern> {
+        match parse_statement(&format!("define p as {}", text))? {
+            Define(_, pattern) => Ok(pattern),
+            _ => unreachable!(),
+        }
+    }
+
+    fn compile(text: &str, lines: &[&str]) -> Result<Dfa> {
+        let mut map = definitions(lines)?;
+        let pattern = pattern(text)?;
+        map.remove("p");
+        let alphabet: Vec<String> = ["a", "b", "c"].iter().map(|l| l.to_string()).collect();
+        Dfa::from_pattern(&pattern, &map, &alphabet)
+    }
+
+    #[test]
+    fn operators() -> Result<()> {
+        let dfa = compile("\"a\"+ \"b\"? | \"c\"*", &[])?;
+
+        assert!(dfa.accepts(&["a"]));
+        assert!(dfa.accepts(&["a", "a", "b"]));
+        assert!(dfa.accepts(&["c", "c"]));
+        assert!(dfa.accepts(&[]));
+        assert!(!dfa.accepts(&["b"]));
+        assert!(!dfa.accepts(&["a", "c"]));
+        Ok(())
+    }
+
+    #[test]
+    fn variables() -> Result<()> {
+        let dfa = compile("xy \"z\" epsilon", &["define xy as \"x\" | \"y\""])?;
+
+        assert!(dfa.accepts(&["x", "z"]));
+        assert!(dfa.accepts(&["y", "z"]));
+        assert!(!dfa.accepts(&["z"]));
+        Ok(())
+    }
+
+    #[test]
+    fn repetition() -> Result<()> {
+        let exact = compile("(\"a\" \"b\"){2}", &[])?;
+        assert!(exact.accepts(&["a", "b", "a", "b"]));
+        assert!(!exact.accepts(&["a", "b"]));
+
+        let bounded = compile("\"a\"{1,3} \"b\"{0,}", &[])?;
+        assert!(bounded.accepts(&["a"]));
+        assert!(bounded.accepts(&["a", "a", "a", "b", "b"]));
+        assert!(!bounded.accepts(&["a", "a", "a", "a"]));
+        assert!(!bounded.accepts(&["b"]));
+
+        let none = compile("\"a\"{0}", &[])?;
+        assert!(none.accepts(&[]));
+        assert!(!none.accepts(&["a"]));
+        Ok(())
+    }
+
+    #[test]
+    fn wildcards() -> Result<()> {
+        let any = compile(". \"a\"", &[])?;
+        assert!(any.accepts(&["b", "a"]));
+        assert!(any.accepts(&["c", "a"]));
+        assert!(!any.accepts(&["a"]));
+
+        let not = compile("![\"a\", \"b\"]+", &[])?;
+        assert!(not.accepts(&["c", "c"]));
+        assert!(!not.accepts(&["c", "a"]));
+        assert!(!not.accepts(&["d"]));
+        Ok(())
+    }
+
+    #[test]
+    fn special_labels() -> Result<()> {
+        let dfa = compile("\"epsilon\" \"a\"", &[])?;
+
+        assert!(dfa.accepts(&["epsilon", "a"]));
+        assert!(!dfa.accepts(&["a"]));
+
+        let dfa = compile("^\"a\" \"b\"^-1 \"c^-1\"", &[])?;
+        assert!(dfa.accepts(&[&inverse("a"), &inverse("b"), "c^-1"]));
+        assert!(!dfa.accepts(&["a^-1", "b^-1", &inverse("c")]));
+
+        let dfa = compile("\"^a\" \"<b>\"", &[])?;
+        assert!(dfa.accepts(&[&label_key("^a"), &label_key("<b>")]));
+        assert!(!dfa.accepts(&[&inverse("a"), &nonterminal("b")]));
+        Ok(())
+    }
+
+    #[test]
+    fn errors() -> Result<()> {
+        let undefined = compile("x", &[]).err().unwrap();
+        assert_eq!(
+            Some(&PatternError::UndefinedVariable("x".to_string())),
+            undefined.downcast_ref()
+        );
+
+        let recursive = compile("a", &["define a as \"x\" a"]).err().unwrap();
+        assert_eq!(
+            Some(&PatternError::RecursiveDefinition("a".to_string())),
+            recursive.downcast_ref()
+        );
+
+        let descending = compile("\"a\"{3,1}", &[]).err().unwrap();
+        assert_eq!(
+            Some(&PatternError::DescendingRepetition(3, 1)),
+            descending.downcast_ref()
+        );
+
+        let long = compile("\"a\"{2,1000000000}", &[]).err().unwrap();
+        assert_eq!(
+            Some(&PatternError::LongRepetition(1000000000)),
+            long.downcast_ref()
+        );
+        assert!(compile("\"a\"{1000000000,}", &[]).is_err());
+        assert!(compile("\"a\"{1000}", &[]).is_ok());
+
+        let nested = compile("((\"a\"{1000}){1000}){1000}", &[]).err().unwrap();
+        assert_eq!(
+            Some(&PatternError::LongRepetition(1000)),
+            nested.downcast_ref()
+        );
+        let defined = compile("a{1000}", &["define a as \"a\"{100} | \"b\""]);
+        assert!(defined.is_err());
+        assert!(compile("(\"a\"{100}){100}", &[]).is_err());
+        assert!(compile("(\"a\"{10}){100}", &[]).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn macros_expansion() -> Result<()> {
+        let map = macros(&[
+            "define within(p, q) as p* q",
+            "define f(x) as x y",
+            "define g(y) as f(y) y",
+            "define r(x) as x r(x) | epsilon",
+        ])?;
+
+        assert_eq!(
+            pattern("\"a\"* (\"b\"* \"c\")")?,
+            expand(&pattern("within(\"a\", within(\"b\", \"c\") )")?, &map)?
+        );
+        // Arguments do not capture the names of the body and the other way round
+        assert_eq!(
+            pattern("(\"a\" y) \"a\"")?,
+            expand(&pattern("g(\"a\")")?, &map)?
+        );
+
+        let errors = vec![
+            (
+                "within(\"a\")",
+                PatternError::WrongArity("within".to_string(), 2, 1),
+            ),
+            ("\"a\" f", PatternError::WrongArity("f".to_string(), 1, 0)),
+            ("h(\"a\")", PatternError::UndefinedMacro("h".to_string())),
+            (
+                "r(\"a\")",
+                PatternError::RecursiveDefinition("r".to_string()),
+            ),
+        ];
+        for (text, error) in errors {
+            let result = expand(&pattern(text)?, &map);
+            assert_eq!(Some(&error), result.err().unwrap().downcast_ref());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn recursion() -> Result<()> {
+        let map = definitions(&[
+            "define s as \"a\" t | epsilon",
+            "define t as s \"b\"",
+            "define u as s | v",
+            "define v as \"c\"",
+            "define w as w",
+        ])?;
+
+        let recursive = recursive_definitions(&Var("u".to_string()), &map)?;
+        let expected: HashSet<String> = ["s", "t"].iter().map(|s| s.to_string()).collect();
+        assert_eq!(expected, recursive);
+
+        assert!(recursive_definitions(&Var("v".to_string()), &map)?.is_empty());
+        assert!(recursive_definitions(&Var("x".to_string()), &map).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn labels() -> Result<()> {
+        let map = definitions(&[
+            "define s as \"a\" s ^\"b\" | epsilon",
+            "define any as .",
+        ])?;
+
+        let expected: HashSet<String> = ["a", "b", "c"].iter().map(|s| s.to_string()).collect();
+        assert_eq!(Some(expected), pattern_labels(&pattern("s \"c\"*")?, &map));
+        assert_eq!(None, pattern_labels(&pattern("\"a\" | any")?, &map));
+        Ok(())
+    }
+}
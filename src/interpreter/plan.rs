@@ -0,0 +1,493 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+
+use anyhow::{Error, Result};
+
+use crate::compute::dfa::Dfa;
+use crate::compute::graph::{inverted, label_key, Graph};
+use crate::compute::rfa::Rfa;
+use crate::interpreter::pattern::{
+    expand, expand_definitions, pattern_labels, recursive_definitions,
+};
+use crate::interpreter::Interpreter;
+use crate::syntax::ast::*;
+use crate::compute::string::literal;
+
+/// Vertices and edges by label of a graph expression, estimated without computing it:
+/// a Kronecker product has the products of the numbers of edges, a union their sums.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Estimate {
+    pub size: usize,
+    pub edges: HashMap<String, usize>,
+}
+
+impl Estimate {
+    pub fn of(graph: &Graph) -> Estimate {
+        Estimate {
+            size: graph.size,
+            edges: graph.get_stats(),
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.edges.values().sum()
+    }
+
+    /// Labels with edges, sorted.
+    fn alphabet(&self) -> Vec<String> {
+        let mut labels: Vec<String> = self
+            .edges
+            .iter()
+            .filter(|(_, edges)| **edges > 0)
+            .map(|(label, _)| label.clone())
+            .collect();
+        labels.sort();
+        labels
+    }
+
+    fn kronecker(&self, other: &Estimate) -> Estimate {
+        Estimate {
+            size: self.size * other.size,
+            edges: self
+                .edges
+                .iter()
+                .filter_map(|(label, n)| other.edges.get(label).map(|m| (label.clone(), n * m)))
+                .collect(),
+        }
+    }
+
+    fn union(&self, other: &Estimate) -> Estimate {
+        let mut edges = self.edges.clone();
+        for (label, n) in &other.edges {
+            *edges.entry(label.clone()).or_insert(0) += n;
+        }
+        Estimate {
+            size: self.size.max(other.size),
+            edges,
+        }
+    }
+
+    fn restricted<F: Fn(&str) -> bool>(&self, keep: F) -> Estimate {
+        Estimate {
+            size: self.size,
+            edges: self
+                .edges
+                .iter()
+                .filter(|(label, _)| keep(label))
+                .map(|(label, n)| (label.clone(), *n))
+                .collect(),
+        }
+    }
+
+    /// The estimate of `Graph::with_inverses`.
+    fn with_inverses<'a, I: IntoIterator<Item = &'a String>>(&self, keys: I) -> Estimate {
+        let mut estimate = Estimate {
+            size: self.size,
+            edges: self
+                .edges
+                .iter()
+                .map(|(label, n)| (label_key(label).into_owned(), *n))
+                .collect(),
+        };
+        for key in keys {
+            if let Some(n) = inverted(key).and_then(|label| self.edges.get(label)) {
+                estimate.edges.insert(key.clone(), *n);
+            }
+        }
+        estimate
+    }
+}
+
+impl Display for Estimate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} vertices, {} edges", self.size, self.total())
+    }
+}
+
+/// Order the operands of an intersection are multiplied in: by their numbers of edges,
+/// so that the intermediate Kronecker products are as small as possible.
+pub fn intersection_order(estimates: &[Estimate]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..estimates.len()).collect();
+    order.sort_by_key(|i| estimates[*i].total());
+    order
+}
+
+/// Renames the vertices of the Kronecker product of graphs of `sizes` multiplied in `order`
+/// to the ids they have in the product of the graphs multiplied in the given order.
+pub fn reordering(sizes: &[usize], order: &[usize]) -> impl Fn(usize) -> usize {
+    let sizes = sizes.to_vec();
+    let order = order.to_vec();
+    move |mut vertex| {
+        let mut digits = vec![0; sizes.len()];
+        for i in order.iter().rev() {
+            digits[*i] = vertex % sizes[*i];
+            vertex /= sizes[*i];
+        }
+        digits
+            .iter()
+            .zip(&sizes)
+            .fold(0, |id, (digit, size)| id * size + digit)
+    }
+}
+
+/// Labels of the label conditions of `list`, `None` if a `label matches` can need any of them.
+fn condition_labels(list: &ListExpr) -> Option<HashSet<String>> {
+    let mut labels = HashSet::new();
+    let mut stack = list.conditions();
+    while let Some(condition) = stack.pop() {
+        match condition {
+            LabelIs(label) => {
+                labels.insert(label.clone());
+            }
+            LabelIn(ls) => labels.extend(ls.iter().cloned()),
+            LabelMatches(_) => return None,
+            And(b1, b2) | Or(b1, b2) => {
+                stack.push(b1);
+                stack.push(b2);
+            }
+            Not(b) => stack.push(b),
+            _ => {}
+        }
+    }
+    Some(labels)
+}
+
+/// Whether every edge satisfying the condition has one of the labels it names.
+fn requires_label(condition: &BoolExpr) -> bool {
+    match condition {
+        LabelIs(_) | LabelIn(_) => true,
+        And(b1, b2) => requires_label(b1) || requires_label(b2),
+        Or(b1, b2) => requires_label(b1) && requires_label(b2),
+        _ => false,
+    }
+}
+
+/// Labels the graphs of a `get` from a graph can be restricted to: when a condition
+/// requires a label of every listed edge, the edges of labels no condition names are dropped
+/// before the graph expression is computed. `None` to keep every label.
+pub fn filtered_labels(object: &ObjectExpr) -> Option<HashSet<String>> {
+    let list = match object {
+        List(list) | Count(list) => list,
+        CountBy(list, key) | Distinct(key, list) if *key != Label => list,
+        _ => return None,
+    };
+    let pushed = *list.object() == Edges && list.conditions().into_iter().any(requires_label);
+    if pushed {
+        condition_labels(list)
+    } else {
+        None
+    }
+}
+
+/// Labels the graphs of a path query can be restricted to: the labels of the pattern
+/// and of the conditions, other edges can not change the found pairs.
+/// `None` to keep every label, as for a pattern with a wildcard or a list of labels.
+pub fn query_labels(
+    object: Option<&ObjectExpr>,
+    pattern: Option<HashSet<String>>,
+) -> Option<HashSet<String>> {
+    let list = match object {
+        Some(CountBy(_, Label)) | Some(Distinct(Label, _)) => return None,
+        Some(List(list)) | Some(Count(list)) | Some(CountBy(list, _)) | Some(Distinct(_, list)) => {
+            list
+        }
+        None => return pattern,
+    };
+    if *list.object() == Labels {
+        return None;
+    }
+    let mut labels = pattern?;
+    labels.extend(condition_labels(list)?);
+    Some(labels)
+}
+
+fn kept_labels(labels: &Option<HashSet<String>>) -> String {
+    match labels {
+        Some(labels) => {
+            let mut labels: Vec<&String> = labels.iter().collect();
+            labels.sort();
+            let labels: Vec<String> = labels.iter().map(|l| literal(l)).collect();
+            format!("labels kept: [{}]", labels.join(", "))
+        }
+        None => "labels kept: all".to_string(),
+    }
+}
+
+/// How the listed objects are computed from the selected graph or pairs.
+fn evaluation(object: &ObjectExpr) -> String {
+    match object {
+        List(list) | Count(list) => match list.conditions().len() {
+            0 => "result: listed without conditions".to_string(),
+            n => format!("result: {} conditions checked for every listed item", n),
+        },
+        CountBy(_, Label) | Distinct(Label, _) => {
+            "result: every label matrix masked with the listed edges".to_string()
+        }
+        CountBy(_, Begin) | Distinct(Begin, _) => {
+            "result: plus reduction of the rows of the listed edges".to_string()
+        }
+        CountBy(_, End) | Distinct(End, _) => {
+            "result: plus reduction of the columns of the listed edges".to_string()
+        }
+    }
+}
+
+impl Interpreter {
+    /// Steps of a `get`: the labels its graphs are restricted to, the graph expression
+    /// with estimated sizes, the automaton of the pattern and the Kronecker product with it.
+    /// Graphs are loaded to know their sizes, products are not computed.
+    pub(super) fn explain(&mut self, object: &ObjectExpr, source: &Source) -> Result<Vec<String>> {
+        let mut plan = Vec::new();
+        match source {
+            Direct(expr) => {
+                let labels = filtered_labels(object);
+                plan.push(kept_labels(&labels));
+                let graph = self.estimate(expr, labels.as_ref(), 0, &mut plan)?;
+                if let List(list) | Count(list) = object {
+                    if *list.object() == Pairs {
+                        plan.push(format!(
+                            "closure: repeated squaring of the {0} x {0} adjacency matrix",
+                            graph.size
+                        ));
+                    }
+                }
+            }
+            Apply(pattern, expr) => {
+                let pattern = &expand(pattern, &self.macros)?;
+                let patterns = expand_definitions(pattern, &self.patterns, &self.macros)?;
+                let recursive = recursive_definitions(pattern, &patterns)?;
+                let labels = query_labels(Some(object), pattern_labels(pattern, &patterns));
+                plan.push(kept_labels(&labels));
+                let graph = self.estimate(expr, labels.as_ref(), 0, &mut plan)?;
+                let alphabet = graph.alphabet();
+                if recursive.is_empty() {
+                    let request = Dfa::from_pattern(pattern, &patterns, &alphabet)?;
+                    let automaton = Estimate::of(&request.graph);
+                    plan.push(format!(
+                        "regular path query: minimal DFA of {} states and {} edges",
+                        automaton.size,
+                        automaton.total()
+                    ));
+                    let product = graph
+                        .with_inverses(request.graph.matrices.keys())
+                        .kronecker(&automaton);
+                    plan.push(format!(
+                        "kronecker product of the graph and the DFA: {} * {} = {}",
+                        graph.size, automaton.size, product
+                    ));
+                    plan.push(format!(
+                        "closure: repeated squaring of the {0} x {0} adjacency matrix",
+                        product.size
+                    ));
+                } else {
+                    let rfa = Rfa::from_pattern(pattern, &patterns, &recursive, &alphabet)?;
+                    let automaton = Estimate::of(&rfa.dfa.graph);
+                    let mut nonterminals: Vec<&String> = rfa.nonterminals.iter().collect();
+                    nonterminals.sort();
+                    let nonterminals: Vec<&str> = nonterminals.iter().map(|n| n.as_str()).collect();
+                    plan.push(format!(
+                        "context-free path query: RFA of {} states and {} edges for {}",
+                        automaton.size,
+                        automaton.total(),
+                        nonterminals.join(", ")
+                    ));
+                    let product =
+                        automaton.kronecker(&graph.with_inverses(rfa.dfa.graph.matrices.keys()));
+                    plan.push(format!(
+                        "kronecker product of the RFA and the graph: {} * {} = {} in the first iteration",
+                        automaton.size, graph.size, product
+                    ));
+                    plan.push(format!(
+                        "closure: repeated squaring of the {0} x {0} adjacency matrix \
+                         in every iteration, until no nonterminal edge is added",
+                        product.size
+                    ));
+                }
+            }
+        }
+        plan.push(evaluation(object));
+        Ok(plan)
+    }
+
+    /// Adds a line per node of the expression, indented by its depth, children after their parent.
+    fn estimate(
+        &mut self,
+        expr: &GraphExpr,
+        labels: Option<&HashSet<String>>,
+        depth: usize,
+        plan: &mut Vec<String>,
+    ) -> Result<Estimate> {
+        let mut children = Vec::new();
+        let (description, estimate) = match expr {
+            Intersection(graphs) if graphs.len() == 1 => {
+                return self.estimate_simple(&graphs[0], labels, depth, plan);
+            }
+            Intersection(graphs) => {
+                let mut operands = Vec::new();
+                for graph in graphs {
+                    let mut lines = Vec::new();
+                    let estimate = self.estimate_simple(graph, labels, depth + 1, &mut lines)?;
+                    operands.push((estimate, lines));
+                }
+                let estimates: Vec<Estimate> = operands.iter().map(|(e, _)| e.clone()).collect();
+                let order = intersection_order(&estimates);
+                let mut product: Option<Estimate> = None;
+                for i in &order {
+                    product = Some(match product {
+                        Some(product) => product.kronecker(&estimates[*i]),
+                        None => estimates[*i].clone(),
+                    });
+                    children.extend(operands[*i].1.iter().cloned());
+                }
+                let names: Vec<String> = order.iter().map(|i| graphs[*i].to_string()).collect();
+                let product = product.ok_or_else(|| Error::msg("empty intersection"))?;
+                (
+                    format!("intersection in the order {}", names.join(" & ")),
+                    product,
+                )
+            }
+            Union(e1, e2) => {
+                let g1 = self.estimate(e1, labels, depth + 1, &mut children)?;
+                let g2 = self.estimate(e2, labels, depth + 1, &mut children)?;
+                ("union".to_string(), g1.union(&g2))
+            }
+            Difference(e1, e2) => {
+                let g1 = self.estimate(e1, labels, depth + 1, &mut children)?;
+                self.estimate(e2, labels, depth + 1, &mut children)?;
+                ("difference".to_string(), g1)
+            }
+        };
+        plan.push(format!(
+            "{}{}: {}",
+            "  ".repeat(depth),
+            description,
+            estimate
+        ));
+        plan.extend(children);
+        Ok(estimate)
+    }
+
+    fn estimate_simple(
+        &mut self,
+        graph: &SimpleGraph,
+        labels: Option<&HashSet<String>>,
+        depth: usize,
+        plan: &mut Vec<String>,
+    ) -> Result<Estimate> {
+        let mut children = Vec::new();
+        let (description, estimate) = match graph {
+            GraphName(name) | WithEnds { graph: name, .. } => {
+                if let WithEnds {
+                    initials, finals, ..
+                } = graph
+                {
+                    for (ends, vertices) in &[("initials", initials), ("finals", finals)] {
+                        if let Reachable(..) | Of(_) = vertices {
+                            children.push(format!(
+                                "{}{} found by the query {}",
+                                "  ".repeat(depth + 1),
+                                ends,
+                                vertices
+                            ));
+                        }
+                    }
+                }
+                let (description, estimate) = match self.graph_definitions.get(name).cloned() {
+                    Some(expr) => match self.computed.get(&expr) {
+                        Some(dfa) => (format!("defined graph {}", name), Estimate::of(&dfa.graph)),
+                        None => (
+                            format!("defined graph {}, computed first", name),
+                            self.estimate(&expr, None, depth + 1, &mut children)?,
+                        ),
+                    },
+                    None => (format!("graph {}", name), Estimate::of(self.graph(name)?)),
+                };
+                match labels {
+                    Some(labels) => (
+                        format!("{} restricted to the kept labels", description),
+                        estimate.restricted(|l| labels.contains(l)),
+                    ),
+                    None => (description, estimate),
+                }
+            }
+            Reverse(graph) => (
+                "reverse".to_string(),
+                self.estimate_simple(graph, labels, depth + 1, &mut children)?,
+            ),
+            Restricted(graph, ls) => {
+                let estimate = self.estimate_simple(graph, labels, depth + 1, &mut children)?;
+                let names: Vec<String> = ls.iter().map(|l| literal(l)).collect();
+                (
+                    format!("restricted to labels [{}]", names.join(", ")),
+                    estimate.restricted(|l| ls.iter().any(|kept| kept == l)),
+                )
+            }
+            Nested(expr) => return self.estimate(expr, labels, depth, plan),
+        };
+        plan.push(format!(
+            "{}{}: {}",
+            "  ".repeat(depth),
+            description,
+            estimate
+        ));
+        plan.extend(children);
+        Ok(estimate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use crate::compute::dfa::Dfa;
+    use crate::compute::graph::{ExtractPairs, Graph};
+    use crate::compute::limits::ExecutionLimits;
+
+    use super::*;
+
+    fn dfa(edges: &[(usize, usize, &str)], ends: &[usize]) -> Dfa {
+        let edges: Vec<_> = edges
+            .iter()
+            .map(|(from, to, label)| (*from, *to, label.to_string()))
+            .collect();
+        let ends: HashSet<usize> = ends.iter().cloned().collect();
+        Dfa {
+            graph: Graph::build(&edges),
+            initials: ends.clone(),
+            finals: ends,
+        }
+    }
+
+    #[test]
+    fn reordered_intersection() -> Result<()> {
+        let dfas = [
+            dfa(&[(0, 1, "a"), (1, 2, "a"), (2, 0, "b"), (0, 2, "b")], &[0]),
+            dfa(&[(0, 0, "a"), (0, 1, "b")], &[1]),
+            dfa(&[(1, 0, "a"), (0, 1, "a"), (1, 1, "b")], &[0, 1]),
+        ];
+        let sizes: Vec<usize> = dfas.iter().map(|dfa| dfa.graph.size).collect();
+        let estimates: Vec<Estimate> = dfas.iter().map(|dfa| Estimate::of(&dfa.graph)).collect();
+        let order = intersection_order(&estimates);
+        assert_eq!(vec![1, 2, 0], order);
+
+        let limits = ExecutionLimits::default();
+        let expected = dfas[0]
+            .intersection(&dfas[1], &limits)?
+            .intersection(&dfas[2], &limits)?;
+        let reordered = dfas[1]
+            .intersection(&dfas[2], &limits)?
+            .intersection(&dfas[0], &limits)?
+            .renumbered(reordering(&sizes, &order));
+        assert_eq!(expected.initials, reordered.initials);
+        assert_eq!(expected.finals, reordered.finals);
+        for label in &["a", "b"] {
+            let pairs = |dfa: &Dfa| {
+                let mut pairs = dfa.graph.get(*label).unwrap().extract_pairs();
+                pairs.sort();
+                pairs
+            };
+            assert_eq!(pairs(&expected), pairs(&reordered));
+        }
+        Ok(())
+    }
+}
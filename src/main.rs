@@ -12,27 +12,58 @@ use std::env;
 use std::fs::File;
 use std::io::Read;
 use std::str::FromStr;
+use std::time::{Duration, Instant};
 
 use anyhow::Result;
 
 use crate::compute::dfa::Dfa;
 use crate::compute::graph::Graph;
+use crate::compute::limits::ExecutionLimits;
+use crate::interpreter::{Interpreter, Value};
 use crate::measure::write_csv;
 use crate::ll::Data;
+use crate::output::Format;
+use crate::repl::Repl;
 
 mod compute;
+mod interpreter;
 mod syntax;
 mod measure;
 mod ll;
+mod output;
+mod repl;
 
 static HELP: &'static str = concat!("Arguments: (stats *path to graph file* *path to request file*)\n",
     "\t| (measure *path*)\n",
     "\t| (check *path*)\n",
     "\t| (dot *path*)\n",
+    "\t| (fmt *path*)\n",
+    "\t| (lint *path*)\n",
+    "\t| (run *path*)\n",
+    "\t| repl\n",
+    "Options: --format (text | json | csv | tsv) for stats and run\n",
+    "\t--timeout *seconds*, --max-nvals *n* and --max-iterations *n* bound the closures of run and of each repl statement\n",
 );
 
 fn main() -> Result<()> {
-    let mut args = env::args().skip(1);
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let format = match args.iter().position(|a| a == "--format") {
+        Some(i) if i + 1 < args.len() => {
+            let format = args.remove(i + 1);
+            args.remove(i);
+            Format::from_str(&format)?
+        }
+        Some(_) => panic!("{}", HELP),
+        None => Format::Text,
+    };
+    let timeout = option(&mut args, "--timeout")?.map(Duration::from_secs_f64);
+    let limits = ExecutionLimits {
+        deadline: None,
+        max_nvals: option(&mut args, "--max-nvals")?,
+        max_iterations: option(&mut args, "--max-iterations")?,
+        cancellation: None,
+    };
+    let mut args = args.into_iter();
     let mut arg = || {
         if let Some(arg) = args.next() { arg } else { panic!(HELP) }
     };
@@ -46,7 +77,8 @@ fn main() -> Result<()> {
 
             let graph = Graph::read_from(graph_path)?;
             let regex = Dfa::read_regex_from(regex_path)?;
-            println!("{:?}", graph.kronecker(&regex.graph).get_stats());
+            let stats = Value::counts(graph.kronecker(&regex.graph).get_stats());
+            format.write(&[stats], &mut std::io::stdout())?;
         }
         "measure" => {
             let path = arg();
@@ -69,7 +101,10 @@ fn main() -> Result<()> {
             if syntax::check(string.as_str())? {
                 println!("valid")
             } else {
-                println!("invalid")
+                println!("invalid");
+                if let Err(e) = syntax::build_ast(string.as_str()) {
+                    println!("{}", e)
+                }
             }
         }
         "dot" => {
@@ -83,6 +118,49 @@ fn main() -> Result<()> {
 
             println!("{}", dot);
         }
+        "fmt" => {
+            let path = arg();
+            let string = match path.as_str() {
+                "-" => read_stdin(),
+                _ => read_file(&path)?,
+            };
+            let ast = syntax::build_ast(string.as_str())?;
+            print!("{}", syntax::to_text(&ast));
+        }
+        "lint" => {
+            let path = arg();
+            let string = match path.as_str() {
+                "-" => read_stdin(),
+                _ => read_file(&path)?,
+            };
+            let ast = syntax::build_ast(string.as_str())?;
+            let problems = syntax::lint(&ast);
+            if problems.is_empty() {
+                println!("no problems found")
+            }
+            for problem in problems {
+                println!("{}", problem)
+            }
+        }
+        "run" => {
+            let path = arg();
+            let mut interpreter = Interpreter::new(env::current_dir()?);
+            interpreter.set_limits(ExecutionLimits {
+                deadline: timeout.map(|timeout| Instant::now() + timeout),
+                ..limits
+            });
+            let values = match path.as_str() {
+                "-" => interpreter.run(&syntax::build_ast(&read_stdin())?)?,
+                _ => interpreter.include(&path)?,
+            };
+            format.write(&values, &mut std::io::stdout())?;
+        }
+        "repl" => {
+            let mut repl = Repl::new(Interpreter::new(env::current_dir()?));
+            repl.set_limits(limits, timeout);
+            let stdin = std::io::stdin();
+            repl.run(stdin.lock(), std::io::stdout())?;
+        }
         "ll-table" => {
             let text = read_file(&arg())?;
             //println!("{:?}", cfg);
@@ -104,6 +182,20 @@ fn main() -> Result<()> {
     Ok(())
 }
 
+/// Removes `name` and its value from the arguments.
+fn option<T: FromStr>(args: &mut Vec<String>, name: &str) -> Result<Option<T>>
+    where T::Err: std::error::Error + Send + Sync + 'static {
+    match args.iter().position(|a| a == name) {
+        Some(i) if i + 1 < args.len() => {
+            let value = args.remove(i + 1);
+            args.remove(i);
+            Ok(Some(T::from_str(&value)?))
+        }
+        Some(_) => panic!("{}", HELP),
+        None => Ok(None),
+    }
+}
+
 fn read_file(file: &str) -> Result<String> {
     let mut file = File::open(file)?;
     let mut content = String::new();
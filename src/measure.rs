@@ -8,8 +8,11 @@ use graphblas::BaseTypeMatrix;
 
 use crate::compute::dfa::*;
 use crate::compute::graph::{ExtractPairs, Graph};
+use crate::compute::limits::{ExecutionLimits, LimitExceeded};
 
-static LIST: &[(&str, fn(&mut BaseTypeMatrix<bool>))] = &[
+type Closure = fn(&mut BaseTypeMatrix<bool>, &ExecutionLimits) -> Result<(), LimitExceeded>;
+
+static LIST: &[(&str, Closure)] = &[
     ("square", Graph::close_with_squaring),
     ("adj", Graph::close_with_adjacency_matrix),
 ];
@@ -32,7 +35,7 @@ pub fn write_csv(path: String, csv_path: String, iterations: u64) -> Result<()>
                 let query = Dfa::read_query_from(query_file.path())?;
 
                 for _ in 0..iterations {
-                    let res = measure(&graph, &query);
+                    let res = measure(&graph, &query)?;
 
                     let res = format!(
                         "{},{}/{},{},{},{},{},{},{},{}",
@@ -58,7 +61,7 @@ pub fn write_csv(path: String, csv_path: String, iterations: u64) -> Result<()>
     Ok(())
 }
 
-fn measure(graph: &Graph, request: &Dfa) -> (u128, HashMap<String, (u128, u128, usize)>) {
+fn measure(graph: &Graph, request: &Dfa) -> Result<(u128, HashMap<String, (u128, u128, usize)>)> {
     let mut map = HashMap::<String, (u128, u128, usize)>::new();
 
     let time = Instant::now();
@@ -69,7 +72,7 @@ fn measure(graph: &Graph, request: &Dfa) -> (u128, HashMap<String, (u128, u128,
     for (name, func) in LIST {
         let mut closure = adj.clone();
         let time = Instant::now();
-        func(&mut closure);
+        func(&mut closure, &ExecutionLimits::default())?;
         let close_time = time.elapsed();
 
         let time = Instant::now();
@@ -82,5 +85,5 @@ fn measure(graph: &Graph, request: &Dfa) -> (u128, HashMap<String, (u128, u128,
             (close_time.as_nanos(), pairs_time.as_nanos(), pairs_count),
         );
     }
-    (intersection_time.as_nanos(), map)
+    Ok((intersection_time.as_nanos(), map))
 }
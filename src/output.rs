@@ -0,0 +1,211 @@
+use std::io::Write;
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{Error, Result};
+
+use crate::interpreter::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    Json,
+    Csv,
+    Tsv,
+}
+
+impl FromStr for Format {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Format> {
+        match s {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            "csv" => Ok(Format::Csv),
+            "tsv" => Ok(Format::Tsv),
+            other => Err(Error::msg(format!("unknown format {}", other))),
+        }
+    }
+}
+
+impl Format {
+    /// Format of a file by its extension, `Text` for an unknown one.
+    pub fn for_path(path: &Path) -> Format {
+        path.extension()
+            .and_then(|extension| extension.to_str())
+            .and_then(|extension| extension.parse().ok())
+            .unwrap_or(Format::Text)
+    }
+
+    /// Writes values in this format, JSON as an array of them and CSV/TSV as
+    /// one table with a header per value, separated by empty lines.
+    pub fn write<W: Write>(self, values: &[Value], output: &mut W) -> Result<()> {
+        match self {
+            Format::Text => {
+                for value in values {
+                    write!(output, "{}", value)?;
+                }
+            }
+            Format::Json => {
+                serde_json::to_writer_pretty(&mut *output, values)?;
+                writeln!(output)?;
+            }
+            Format::Csv | Format::Tsv => {
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        writeln!(output)?;
+                    }
+                    for row in rows(value) {
+                        let fields: Vec<String> = row.iter().map(|f| self.field(f)).collect();
+                        let separator = if self == Format::Csv { "," } else { "\t" };
+                        writeln!(output, "{}", fields.join(separator))?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn field(self, field: &str) -> String {
+        match self {
+            Format::Csv if field.contains(&[',', '"', '\n', '\r'][..]) => {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            }
+            Format::Tsv => field
+                .replace('\\', "\\\\")
+                .replace('\t', "\\t")
+                .replace('\n', "\\n")
+                .replace('\r', "\\r"),
+            _ => field.to_string(),
+        }
+    }
+}
+
+/// Header and records of a value as a table.
+fn rows(value: &Value) -> Vec<Vec<String>> {
+    let header = |names: &[&str]| names.iter().map(|n| n.to_string()).collect();
+    let mut rows: Vec<Vec<String>> = Vec::new();
+    match value {
+        Value::Edges(edges) => {
+            rows.push(header(&["from", "to"]));
+            rows.extend(
+                edges
+                    .iter()
+                    .map(|(from, to)| vec![from.to_string(), to.to_string()]),
+            );
+        }
+        Value::Count(count) => {
+            rows.push(header(&["count"]));
+            rows.push(vec![count.to_string()]);
+        }
+        Value::Vertices(vertices) => {
+            rows.push(header(&["vertex"]));
+            rows.extend(vertices.iter().map(|v| vec![v.to_string()]));
+        }
+        Value::Labels(labels) => {
+            rows.push(header(&["label"]));
+            rows.extend(labels.iter().map(|l| vec![l.clone()]));
+        }
+        Value::Pairs(pairs) => {
+            rows.push(header(&["nonterminal", "from", "to"]));
+            for (nonterminal, edges) in pairs {
+                rows.extend(
+                    edges.iter().map(|(from, to)| {
+                        vec![nonterminal.clone(), from.to_string(), to.to_string()]
+                    }),
+                );
+            }
+        }
+        Value::Counts(counts) => {
+            rows.push(header(&["label", "count"]));
+            rows.extend(
+                counts
+                    .iter()
+                    .map(|(label, count)| vec![label.clone(), count.to_string()]),
+            );
+        }
+        Value::Graphs(names) => {
+            rows.push(header(&["graph"]));
+            rows.extend(names.iter().map(|n| vec![n.clone()]));
+        }
+        Value::Plan(steps) => {
+            rows.push(header(&["step"]));
+            rows.extend(steps.iter().map(|s| vec![s.clone()]));
+        }
+        Value::VertexCounts(counts) => {
+            rows.push(header(&["vertex", "count"]));
+            rows.extend(
+                counts
+                    .iter()
+                    .map(|(vertex, count)| vec![vertex.to_string(), count.to_string()]),
+            );
+        }
+    }
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use anyhow::Result;
+
+    use crate::compute::graph::Graph;
+    use crate::compute::limits::ExecutionLimits;
+    use crate::compute::rfa::Rfa;
+
+    use super::*;
+
+    fn write(format: Format, values: &[Value]) -> Result<String> {
+        let mut output = Vec::new();
+        format.write(values, &mut output)?;
+        Ok(String::from_utf8(output)?)
+    }
+
+    #[test]
+    fn formats() -> Result<()> {
+        let mut counts = BTreeMap::new();
+        counts.insert("b".to_string(), 1);
+        counts.insert("a,\"x\"".to_string(), 2);
+        let values = vec![
+            Value::Edges(vec![(0, 1), (2, 3)]),
+            Value::Count(2),
+            Value::Counts(counts),
+        ];
+
+        assert_eq!(
+            "from,to\n0,1\n2,3\n\ncount\n2\n\nlabel,count\n\"a,\"\"x\"\"\",2\nb,1\n",
+            write(Format::Csv, &values)?
+        );
+        assert_eq!(
+            "from\tto\n0\t1\n2\t3\n\ncount\n2\n\nlabel\tcount\na,\"x\"\t2\nb\t1\n",
+            write(Format::Tsv, &values)?
+        );
+        assert_eq!(
+            serde_json::json!([
+                {"edges": [[0, 1], [2, 3]]},
+                {"count": 2},
+                {"counts": {"a,\"x\"": 2, "b": 1}},
+            ]),
+            serde_json::from_str::<serde_json::Value>(&write(Format::Json, &values)?)?
+        );
+        assert_eq!(
+            "0 1\n2 3\n2\na,\"x\" 2\nb 1\n",
+            write(Format::Text, &values)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn pairs() -> Result<()> {
+        let graph = Graph::build(&[(0, 1, "a".to_string()), (1, 2, "b".to_string())]);
+        let rfa = Rfa::from_text("S a S b | a b")?;
+        let value = Value::pairs(&graph.cfpq_tensor_product(&rfa, &ExecutionLimits::default())?);
+
+        assert_eq!(
+            "nonterminal,from,to\nS,0,2\n",
+            write(Format::Csv, &[value])?
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,260 @@
+use std::io::{BufRead, Write};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use anyhow::{Error, Result};
+
+use graphblas::MatrixActions;
+
+use crate::compute::limits::ExecutionLimits;
+use crate::interpreter::Interpreter;
+use crate::syntax;
+use crate::syntax::ast::{Script, Statement};
+
+static HELP: &str = concat!(
+    "Enter statements, an unfinished one continues on the next line.\n",
+    ":graphs          loaded graphs\n",
+    ":defs            defined patterns\n",
+    ":dot [statement] AST of the statement or of the last executed one in DOT format\n",
+    ":load *path*     run a script\n",
+    ":time (on | off) print execution time of statements\n",
+    ":history         entered statements, !! or !n runs them again\n",
+    ":quit\n",
+);
+
+pub struct Repl {
+    interpreter: Interpreter,
+    history: Vec<String>,
+    last: Option<Statement>,
+    time: bool,
+    limits: ExecutionLimits,
+    timeout: Option<Duration>,
+}
+
+impl Repl {
+    pub fn new(interpreter: Interpreter) -> Repl {
+        Repl {
+            interpreter,
+            history: Vec::new(),
+            last: None,
+            time: false,
+            limits: ExecutionLimits::default(),
+            timeout: None,
+        }
+    }
+
+    /// Bounds each statement by `limits`, its deadline is `timeout` after the statement starts.
+    pub fn set_limits(&mut self, limits: ExecutionLimits, timeout: Option<Duration>) {
+        self.limits = limits;
+        self.timeout = timeout;
+    }
+
+    pub fn run<R: BufRead, W: Write>(&mut self, input: R, mut output: W) -> Result<()> {
+        let mut buffer = String::new();
+        write!(output, "> ")?;
+        output.flush()?;
+
+        for line in input.lines() {
+            let line = line?;
+            if buffer.is_empty() && line.trim().is_empty() {
+                write!(output, "> ")?;
+                output.flush()?;
+                continue;
+            }
+
+            buffer.push_str(&line);
+            buffer.push('\n');
+            let text = buffer.trim().to_string();
+
+            if !text.starts_with(':') && !text.starts_with('!') && syntax::is_incomplete(&text) {
+                write!(output, "| ")?;
+                output.flush()?;
+                continue;
+            }
+            buffer.clear();
+
+            if text == ":quit" {
+                return Ok(());
+            }
+            if let Err(e) = self.input(&text, &mut output) {
+                writeln!(output, "error: {}", e)?;
+            }
+            write!(output, "> ")?;
+            output.flush()?;
+        }
+
+        Ok(())
+    }
+
+    fn input<W: Write>(&mut self, text: &str, output: &mut W) -> Result<()> {
+        let text = match text {
+            "!!" => self.recall(self.history.len())?,
+            _ if text.starts_with('!') => self.recall(usize::from_str(&text[1..])?)?,
+            _ => text.to_string(),
+        };
+        self.history.push(text.clone());
+
+        if text.starts_with(':') {
+            self.command(&text, output)
+        } else {
+            let statement = syntax::parse_statement(&text)?;
+            self.statement(statement, output)
+        }
+    }
+
+    fn recall(&self, number: usize) -> Result<String> {
+        number
+            .checked_sub(1)
+            .and_then(|i| self.history.get(i))
+            .cloned()
+            .ok_or_else(|| Error::msg(format!("no history entry {}", number)))
+    }
+
+    fn statement<W: Write>(&mut self, statement: Statement, output: &mut W) -> Result<()> {
+        let time = Instant::now();
+        self.interpreter.set_limits(ExecutionLimits {
+            deadline: self.timeout.map(|timeout| time + timeout),
+            ..self.limits.clone()
+        });
+        let values = self.interpreter.execute(&statement);
+        let elapsed = time.elapsed();
+        self.last = Some(statement);
+
+        for value in values? {
+            write!(output, "{}", value)?;
+        }
+        if self.time {
+            writeln!(output, "time: {:?}", elapsed)?;
+        }
+        Ok(())
+    }
+
+    fn command<W: Write>(&mut self, text: &str, output: &mut W) -> Result<()> {
+        let (command, argument) = match text.find(char::is_whitespace) {
+            Some(space) => (&text[..space], text[space..].trim()),
+            None => (text, ""),
+        };
+
+        match command {
+            ":help" => write!(output, "{}", HELP)?,
+            ":graphs" => {
+                for (path, graph) in self.interpreter.graphs() {
+                    let edges: u64 = graph.matrices.values().map(|m| m.nvals()).sum();
+                    writeln!(
+                        output,
+                        "{}: {} vertices, {} edges, {} labels",
+                        path.display(),
+                        graph.size,
+                        edges,
+                        graph.matrices.len()
+                    )?;
+                }
+            }
+            ":defs" => {
+                for definition in self.interpreter.definitions() {
+                    writeln!(output, "{}", definition)?;
+                }
+            }
+            ":dot" => {
+                let statement = match argument {
+                    "" => self
+                        .last
+                        .clone()
+                        .ok_or_else(|| Error::msg("no statement was executed"))?,
+                    _ => syntax::parse_statement(argument)?,
+                };
+                writeln!(output, "{}", syntax::to_dot(&Script::Sequence(vec![statement])))?;
+            }
+            ":load" => self.statement(Statement::Include(argument.to_string()), output)?,
+            ":time" => match argument {
+                "on" => self.time = true,
+                "off" => self.time = false,
+                _ => Err(Error::msg(":time expects on or off"))?,
+            },
+            ":history" => {
+                for (i, entry) in self.history.iter().enumerate() {
+                    writeln!(output, "{:>4}  {}", i + 1, entry.replace('\n', " "))?;
+                }
+            }
+            other => Err(Error::msg(format!("unknown command {}, see :help", other)))?,
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::compute::limits::ExecutionLimits;
+    use crate::interpreter::Interpreter;
+
+    use super::Repl;
+
+    fn run(input: &str) -> Result<String> {
+        let mut repl = Repl::new(Interpreter::new(env!("CARGO_MANIFEST_DIR")));
+        let mut output = Vec::new();
+        repl.run(input.as_bytes(), &mut output)?;
+        Ok(String::from_utf8(output)?.replace("> ", "").replace("| ", ""))
+    }
+
+    #[test]
+    fn statements() -> Result<()> {
+        let output = run(concat!(
+            "connect to test_data.graphs\n",
+            "get count of edges\n",
+            "   from graph1\n",
+            "!2\n",
+        ))?;
+        assert_eq!("3\n3\n", output);
+        Ok(())
+    }
+
+    #[test]
+    fn commands() -> Result<()> {
+        let output = run(concat!(
+            "define a as \"a\"\n",
+            ":defs\n",
+            ":history\n",
+            ":unknown\n",
+            ":quit\n",
+            ":defs\n",
+        ))?;
+        assert_eq!(
+            concat!(
+                "define a as \"a\"\n",
+                "   1  define a as \"a\"\n",
+                "   2  :defs\n",
+                "   3  :history\n",
+                "error: unknown command :unknown, see :help\n",
+            ),
+            output
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn errors() -> Result<()> {
+        let output = run("get edges from g\nget edges from from\n")?;
+        assert_eq!(2, output.lines().filter(|l| l.starts_with("error")).count());
+        Ok(())
+    }
+
+    #[test]
+    fn limits() -> Result<()> {
+        let mut repl = Repl::new(Interpreter::new(env!("CARGO_MANIFEST_DIR")));
+        repl.set_limits(
+            ExecutionLimits {
+                max_iterations: Some(0),
+                ..ExecutionLimits::default()
+            },
+            None,
+        );
+        let input = "connect to test_data.graphs\nget pairs from application of \"a\"* to graph2\n";
+        let mut output = Vec::new();
+        repl.run(input.as_bytes(), &mut output)?;
+        let output = String::from_utf8(output)?;
+        assert!(output.contains("error: closure"), "{}", output);
+        Ok(())
+    }
+}
@@ -1,4 +1,5 @@
 pub use BoolExpr::*;
+pub use GroupKey::*;
 pub use GraphExpr::*;
 pub use ListExpr::*;
 pub use ObjectExpr::*;
@@ -9,30 +10,46 @@ pub use Source::*;
 pub use Statement::*;
 pub use Vertices::*;
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Script {
     Sequence(Vec<Statement>),
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Statement {
     Connect(Vec<String>),
     Define(String, Pattern),
+    /// `define name(p, ...) as pattern`, a pattern with parameters expanded at every call.
+    Macro(String, Vec<String>, Pattern),
+    /// `define name as graph ...`, a graph expression used by name in later statements.
+    DefineGraph(String, GraphExpr),
     Get(ObjectExpr, Source),
+    /// `explain get ...`, the plan of the `get` instead of its result.
+    Explain(ObjectExpr, Source),
+    /// Writes the result of a `get` to a file.
+    Save(ObjectExpr, Source, String),
+    /// Writes the edges of a graph, or of a query result, to a file in the edge list format.
+    SaveGraph(Source, String),
+    /// Runs another script, its path is relative to the including one.
+    Include(String),
+    /// `list graphs`, the names of the graphs of the connected catalog.
+    ListGraphs,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Source {
     Apply(Pattern, GraphExpr),
     Direct(GraphExpr),
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum GraphExpr {
     Intersection(Vec<SimpleGraph>),
+    Union(Box<GraphExpr>, Box<GraphExpr>),
+    Difference(Box<GraphExpr>, Box<GraphExpr>),
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum SimpleGraph {
     GraphName(String),
     WithEnds {
@@ -40,50 +57,105 @@ pub enum SimpleGraph {
         finals: Vertices,
         graph: String,
     },
+    Reverse(Box<SimpleGraph>),
+    Restricted(Box<SimpleGraph>, Vec<String>),
+    /// `(g | h)` as an operand of `&`, `reverse` or `restricted to`.
+    /// Parentheses around a single graph or a whole operand of `|` and `-` are not kept.
+    Nested(Box<GraphExpr>),
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Vertices {
     Set(Vec<usize>),
     Range { from: usize, to: usize },
     EmptySet,
+    /// `(vertices reachable from [0] by "a"*)`, ends of the paths of the pattern
+    /// from the vertices in the graph the ends are given for.
+    Reachable(Box<Vertices>, Pattern),
+    /// `(vertices of source)`, the vertices `get vertices from source` lists.
+    Of(Box<Source>),
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ObjectExpr {
     Count(ListExpr),
     List(ListExpr),
+    /// `count of edges group by label`, numbers of the listed edges or pairs by the key.
+    CountBy(ListExpr, GroupKey),
+    /// `distinct labels of edges`, the different keys of the listed edges or pairs.
+    Distinct(GroupKey, ListExpr),
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupKey {
+    Label,
+    Begin,
+    End,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ListExpr {
     Edges,
+    /// `vertices`, named apart from the `Vertices` of graph ends.
+    VertexList,
+    Labels,
+    Pairs,
     Filter(Box<ListExpr>, BoolExpr),
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum BoolExpr {
     LabelIs(String),
+    LabelIn(Vec<String>),
+    /// A regular expression the whole label is matched against.
+    LabelMatches(String),
     BeginIs(VertexVariant),
     EndIs(VertexVariant),
+    BeginIn(Vertices),
+    EndIn(Vertices),
+    BeginCompare(Comparison, usize),
+    EndCompare(Comparison, usize),
+    /// `begin = end`
+    SelfLoop,
+    VertexIs(VertexVariant),
     And(Box<BoolExpr>, Box<BoolExpr>),
     Or(Box<BoolExpr>, Box<BoolExpr>),
     Not(Box<BoolExpr>),
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum VertexVariant {
     Initial,
     Final,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Equal,
+    NotEqual,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Pattern {
     Term(String),
     Var(String),
     Star(Box<Pattern>),
     Plus(Box<Pattern>),
     Maybe(Box<Pattern>),
+    /// `p{n}`, `p{n,m}` and `p{n,}`: from `n` to `m` repetitions, `None` for no upper bound.
+    Repeat(Box<Pattern>, usize, Option<usize>),
+    /// `^"a"` and `"a"^-1`, an edge of the label traversed backwards.
+    Inverse(String),
+    /// `.`, any label of the queried graph.
+    AnyLabel,
+    /// `![...]`, any label of the queried graph except the given ones.
+    NotLabels(Vec<String>),
     Alt(Box<Pattern>, Box<Pattern>),
     Seq(Vec<Pattern>),
+    /// `name(p, ...)`, a call of a parameterised definition.
+    Call(String, Vec<Pattern>),
 }
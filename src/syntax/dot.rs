@@ -40,7 +40,17 @@ impl ToDot for Statement {
                 ctx.vertex(&format!("Connect to {}", name))
             }
             Define(name, pattern) => ctx.op(&format!("Define {}", name), pattern),
+            DefineGraph(name, graph) => ctx.op(&format!("Define graph {}", name), graph),
+            Macro(name, parameters, pattern) => ctx.op(
+                &format!("Define {}({})", name, parameters.join(", ")),
+                pattern,
+            ),
             Get(o, g) => ctx.binop("Get", o, g),
+            Explain(o, g) => ctx.binop("Explain", o, g),
+            Save(o, g, file) => ctx.binop(&format!("Save to '{}'", file), o, g),
+            SaveGraph(g, file) => ctx.op(&format!("Save graph to '{}'", file), g),
+            Include(file) => ctx.vertex(&format!("Include '{}'", file)),
+            ListGraphs => ctx.vertex("List graphs"),
         }
     }
 }
@@ -58,6 +68,8 @@ impl ToDot for GraphExpr {
     fn to_dot(&self, ctx: &mut DotContext) -> usize {
         match self {
             Intersection(gs) => ctx.fold_op("&", gs),
+            Union(g1, g2) => ctx.binop("|", g1.as_ref(), g2.as_ref()),
+            Difference(g1, g2) => ctx.binop("-", g1.as_ref(), g2.as_ref()),
         }
     }
 }
@@ -80,6 +92,12 @@ impl ToDot for SimpleGraph {
                 ctx.edge(id, s_id);
                 id
             }
+            Reverse(graph) => ctx.op("Reverse", graph.as_ref()),
+            Restricted(graph, labels) => ctx.op(
+                &format!("Restricted to labels [{}]", labels.join(", ")),
+                graph.as_ref(),
+            ),
+            Nested(expr) => expr.to_dot(ctx),
         }
     }
 }
@@ -90,6 +108,8 @@ impl ToDot for Vertices {
             Set(set) => ctx.vertex(&format!("{:?}", set)),
             Range { from, to } => ctx.vertex(&format!("[{}..{}]", from, to)),
             EmptySet => ctx.vertex("[]"),
+            Reachable(from, pattern) => ctx.binop("Reachable", from.as_ref(), pattern),
+            Of(source) => ctx.op("Vertices of", source.as_ref()),
         }
     }
 }
@@ -98,6 +118,8 @@ impl ToDot for ObjectExpr {
     fn to_dot(&self, ctx: &mut DotContext) -> usize {
         match self {
             Count(l) => ctx.op("Count", l),
+            CountBy(l, key) => ctx.op(&format!("Count by {:?}", key), l),
+            Distinct(key, l) => ctx.op(&format!("Distinct {:?}", key), l),
             List(l) => l.to_dot(ctx),
         }
     }
@@ -107,6 +129,9 @@ impl ToDot for ListExpr {
     fn to_dot(&self, ctx: &mut DotContext) -> usize {
         match self {
             Edges => ctx.vertex("Edges"),
+            VertexList => ctx.vertex("Vertices"),
+            Labels => ctx.vertex("Labels"),
+            Pairs => ctx.vertex("Pairs"),
             Filter(list, cond) => ctx.binop("Filter", list.as_ref(), cond),
         }
     }
@@ -118,6 +143,14 @@ impl ToDot for BoolExpr {
             LabelIs(s) => ctx.vertex(&format!("label is '{}'", s)),
             BeginIs(s) => ctx.vertex(&format!("begin is {:?}", s)),
             EndIs(s) => ctx.vertex(&format!("end is {:?}", s)),
+            LabelIn(ls) => ctx.vertex(&format!("label in [{}]", ls.join(", "))),
+            LabelMatches(s) => ctx.vertex(&format!("label matches '{}'", s)),
+            BeginIn(vs) => ctx.op("begin in", vs),
+            EndIn(vs) => ctx.op("end in", vs),
+            BeginCompare(c, n) => ctx.vertex(&format!("begin {:?} {}", c, n)),
+            EndCompare(c, n) => ctx.vertex(&format!("end {:?} {}", c, n)),
+            SelfLoop => ctx.vertex("begin = end"),
+            VertexIs(s) => ctx.vertex(&format!("vertex is {:?}", s)),
             And(b1, b2) => ctx.binop("and", b1.as_ref(), b2.as_ref()),
             Or(b1, b2) => ctx.binop("or", b1.as_ref(), b2.as_ref()),
             Not(b) => ctx.op("not", b.as_ref()),
@@ -129,12 +162,23 @@ impl ToDot for Pattern {
     fn to_dot(&self, ctx: &mut DotContext) -> usize {
         match self {
             Term(str) => ctx.vertex(&format!("Terminal '{}'", str)),
+            Inverse(str) => ctx.vertex(&format!("Inverse terminal '{}'", str)),
             Var(str) => ctx.vertex(&format!("Variable '{}'", str)),
             Star(pattern) => ctx.op("*", pattern.as_ref()),
             Plus(pattern) => ctx.op("+", pattern.as_ref()),
             Maybe(pattern) => ctx.op("?", pattern.as_ref()),
+            Repeat(pattern, from, Some(to)) if from == to => {
+                ctx.op(&format!("{{{}}}", from), pattern.as_ref())
+            }
+            Repeat(pattern, from, Some(to)) => {
+                ctx.op(&format!("{{{},{}}}", from, to), pattern.as_ref())
+            }
+            Repeat(pattern, from, None) => ctx.op(&format!("{{{},}}", from), pattern.as_ref()),
+            AnyLabel => ctx.vertex("Any label"),
+            NotLabels(labels) => ctx.vertex(&format!("Any label but [{}]", labels.join(", "))),
             Alt(p1, p2) => ctx.binop("|", p1.as_ref(), p2.as_ref()),
             Seq(vec) => ctx.fold_op(".", vec),
+            Call(name, arguments) => ctx.fold_op(&format!("Call '{}'", name), arguments),
         }
     }
 }
@@ -149,6 +193,7 @@ impl<'a, T> DotContextG<'a, T> {
     pub fn vertex(&mut self, label: &str) -> usize {
         let id = self.last_id;
         self.last_id += 1;
+        let label = label.replace('\\', "\\\\").replace('"', "\\\"");
         self.buffer
             .push_str(format!("\t{}[label=\"{}\"]\n", id, label).as_str());
         id
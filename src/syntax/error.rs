@@ -0,0 +1,137 @@
+use std::fmt::{Display, Formatter};
+
+use lalrpop_util::ParseError;
+
+/// A script that the parser rejected, located by 1-based line and column (in characters).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxError {
+    pub line: usize,
+    pub column: usize,
+    /// Text of the offending token, `None` when the input ended too early.
+    pub token: Option<String>,
+    /// Tokens the parser would accept instead, as they are named in the grammar.
+    pub expected: Vec<String>,
+    /// Why a token the grammar takes is invalid, such as a number too large to be stored.
+    pub reason: Option<String>,
+    source_line: String,
+}
+
+/// Error of the grammar actions: a token at `location` with a value that can not be stored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidValue {
+    pub location: usize,
+    pub token: String,
+    pub reason: &'static str,
+}
+
+impl SyntaxError {
+    pub fn new<T: Display>(text: &str, error: ParseError<usize, T, InvalidValue>) -> SyntaxError {
+        let mut reason = None;
+        let (location, token, expected) = match error {
+            ParseError::InvalidToken { location } => {
+                let token = text[location..].chars().next().map(String::from);
+                (location, token, Vec::new())
+            }
+            ParseError::UnrecognizedEOF { location, expected } => (location, None, expected),
+            ParseError::UnrecognizedToken {
+                token: (location, token, _),
+                expected,
+            } => (location, Some(token.to_string()), expected),
+            ParseError::ExtraToken {
+                token: (location, token, _),
+            } => (location, Some(token.to_string()), Vec::new()),
+            ParseError::User { error } => {
+                reason = Some(error.reason.to_string());
+                (error.location, Some(error.token), Vec::new())
+            }
+        };
+
+        let start = text[..location].rfind('\n').map_or(0, |i| i + 1);
+        let end = text[location..].find('\n').map_or(text.len(), |i| location + i);
+        SyntaxError {
+            line: text[..location].matches('\n').count() + 1,
+            column: text[start..location].chars().count() + 1,
+            token,
+            expected,
+            reason,
+            source_line: text[start..end].trim_end().to_string(),
+        }
+    }
+}
+
+impl Display for SyntaxError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}: ", self.line, self.column)?;
+        match (&self.token, &self.reason) {
+            (Some(token), Some(reason)) => write!(f, "invalid '{}', {}", token, reason)?,
+            (Some(token), None) => write!(f, "unexpected '{}'", token)?,
+            (None, _) => write!(f, "unexpected end of input")?,
+        }
+        if !self.expected.is_empty() {
+            write!(f, ", expected one of {}", self.expected.join(", "))?;
+        }
+
+        let width = self.token.as_ref().map_or(1, |t| t.chars().count().max(1));
+        let number = self.line.to_string();
+        writeln!(f)?;
+        writeln!(f, "{} | {}", number, self.source_line)?;
+        write!(
+            f,
+            "{} | {}{}",
+            " ".repeat(number.len()),
+            " ".repeat(self.column - 1),
+            "^".repeat(width)
+        )
+    }
+}
+
+impl std::error::Error for SyntaxError {}
+
+#[cfg(test)]
+mod tests {
+    use crate::syntax::build_ast;
+
+    use super::SyntaxError;
+
+    fn error(text: &str) -> SyntaxError {
+        build_ast(text)
+            .err()
+            .and_then(|e| e.downcast::<SyntaxError>().ok())
+            .unwrap()
+    }
+
+    #[test]
+    fn location() {
+        let e = error("connect to db\nget edges from from\n");
+        assert_eq!((2, 16), (e.line, e.column));
+        assert_eq!(Some("from".to_string()), e.token);
+        assert!(!e.expected.is_empty());
+        assert!(e
+            .to_string()
+            .ends_with("\n2 | get edges from from\n  |                ^^^^"));
+
+        let e = error("define a as (\"a\"");
+        assert_eq!((1, 17), (e.line, e.column));
+        assert_eq!(None, e.token);
+        assert!(e.expected.contains(&"\")\"".to_string()));
+    }
+
+    #[test]
+    fn invalid_token() {
+        let e = error("define a as \"a\"\n  % ");
+        assert_eq!((2, 3), (e.line, e.column));
+        assert_eq!(Some("%".to_string()), e.token);
+        assert!(e.expected.is_empty());
+    }
+
+    #[test]
+    fn invalid_value() {
+        let e = error("define a as \"a\"\nget edges from g with initials as [1..99999999999999999999]");
+        assert_eq!((2, 39), (e.line, e.column));
+        assert_eq!(Some("99999999999999999999".to_string()), e.token);
+        assert!(e.to_string().starts_with("2:39: invalid '99999999999999999999', number too large"));
+
+        let e = error("define a as \"a\"{99999999999999999999}");
+        assert_eq!((1, 17), (e.line, e.column));
+    }
+}
@@ -0,0 +1,476 @@
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+
+use super::ast::*;
+
+/// A problem found in a script, `statement` is its 1-based position in the script.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Problem {
+    pub statement: usize,
+    pub kind: ProblemKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProblemKind {
+    UndefinedVariable(String),
+    Redefinition(String),
+    GraphBeforeConnect(String),
+    DescendingRange {
+        from: usize,
+        to: usize,
+    },
+    /// `begin is ...`, `end is ...` or `vertex is ...` on a graph whose every vertex is both initial and final.
+    TrivialEnds(String),
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "statement {}: ", self.statement)?;
+        match &self.kind {
+            ProblemKind::UndefinedVariable(name) => write!(f, "undefined variable '{}'", name),
+            ProblemKind::Redefinition(name) => write!(f, "'{}' is already defined", name),
+            ProblemKind::GraphBeforeConnect(name) => {
+                write!(f, "graph '{}' is used before connect", name)
+            }
+            ProblemKind::DescendingRange { from, to } => {
+                write!(f, "range [{}..{}] is empty, {} > {}", from, to, from, to)
+            }
+            ProblemKind::TrivialEnds(condition) => write!(
+                f,
+                "'{}' always holds, the graph has no initials or finals given",
+                condition
+            ),
+        }
+    }
+}
+
+/// Checks a script without executing it.
+/// Definitions may refer to names defined later, patterns of `get` statements only to earlier ones.
+/// Included files are not read, so names and connections that may come from them are not checked.
+pub fn lint(script: &Script) -> Vec<Problem> {
+    let Sequence(statements) = script;
+    let includes = statements.iter().any(|s| matches!(s, Include(_)));
+    let all_definitions: HashSet<&str> = statements
+        .iter()
+        .filter_map(|statement| match statement {
+            Define(name, _) | Macro(name, ..) => Some(name.as_str()),
+            _ => None,
+        })
+        .collect();
+
+    let mut problems = Vec::new();
+    let mut definitions = HashSet::<&str>::new();
+    let mut graph_definitions = HashSet::<&str>::new();
+    let mut connected = false;
+    let mut included = false;
+
+    for (i, statement) in statements.iter().enumerate() {
+        let mut report = |kind| {
+            problems.push(Problem {
+                statement: i + 1,
+                kind,
+            })
+        };
+        let (object, pattern, graph) = match statement {
+            Connect(_) => {
+                connected = true;
+                continue;
+            }
+            ListGraphs => continue,
+            Include(_) => {
+                included = true;
+                continue;
+            }
+            Define(name, pattern) => {
+                if !definitions.insert(name) {
+                    report(ProblemKind::Redefinition(name.clone()));
+                }
+                if !includes {
+                    undefined(pattern, &all_definitions, &mut report);
+                }
+                continue;
+            }
+            Macro(name, parameters, pattern) => {
+                if !definitions.insert(name) {
+                    report(ProblemKind::Redefinition(name.clone()));
+                }
+                if !includes {
+                    let mut scope = all_definitions.clone();
+                    scope.extend(parameters.iter().map(String::as_str));
+                    undefined(pattern, &scope, &mut report);
+                }
+                continue;
+            }
+            DefineGraph(name, graph) => {
+                if !graph_definitions.insert(name) {
+                    report(ProblemKind::Redefinition(name.clone()));
+                }
+                (None, None, graph)
+            }
+            Get(object, source) | Explain(object, source) | Save(object, source, _) => {
+                let (pattern, graph) = parts(source);
+                (Some(object), pattern, graph)
+            }
+            SaveGraph(source, _) => {
+                let (pattern, graph) = parts(source);
+                (None, pattern, graph)
+            }
+        };
+
+        if let (Some(pattern), false) = (pattern, included) {
+            undefined(pattern, &definitions, &mut report);
+        }
+        let mut graphs = Vec::new();
+        leaves(graph, &mut graphs);
+
+        if !connected && !included {
+            let stored = graphs
+                .iter()
+                .map(|graph| graph_name(graph))
+                .find(|name| !graph_definitions.contains(name));
+            if let Some(name) = stored {
+                report(ProblemKind::GraphBeforeConnect(name.to_string()));
+            }
+        }
+
+        let list = object.map(|object| match object {
+            Count(list) | List(list) | CountBy(list, _) | Distinct(_, list) => list,
+        });
+        let mut ranges = Vec::new();
+        // a defined graph may have ends of its own
+        let mut with_ends = false;
+        for graph in graphs {
+            with_ends |= graph_definitions.contains(graph_name(graph));
+            if let WithEnds {
+                initials, finals, ..
+            } = graph
+            {
+                with_ends = true;
+                for vertices in &[initials, finals] {
+                    vertex_ranges(vertices, &mut ranges);
+                    let mut patterns = Vec::new();
+                    query_patterns(vertices, &mut patterns);
+                    if !included {
+                        for pattern in patterns {
+                            undefined(pattern, &definitions, &mut report);
+                        }
+                    }
+                }
+            }
+        }
+
+        if let Some(list) = list {
+            list_ranges(list, &mut ranges);
+        }
+        for (from, to) in ranges.into_iter().filter(|(from, to)| from > to) {
+            report(ProblemKind::DescendingRange { from, to });
+        }
+        if let (false, Some(list)) = (with_ends, list) {
+            let mut conditions = Vec::new();
+            end_conditions(list, &mut conditions);
+            for condition in conditions {
+                report(ProblemKind::TrivialEnds(condition));
+            }
+        }
+    }
+    problems
+}
+
+fn parts(source: &Source) -> (Option<&Pattern>, &GraphExpr) {
+    match source {
+        Apply(pattern, graph) => (Some(pattern), graph),
+        Direct(graph) => (None, graph),
+    }
+}
+
+fn graph_name(graph: &SimpleGraph) -> &str {
+    match graph {
+        GraphName(name) => name,
+        WithEnds { graph, .. } => graph,
+        _ => unreachable!("only leaves are named"),
+    }
+}
+
+/// Named graphs of an expression, with their ends, left to right.
+fn leaves<'a>(graph: &'a GraphExpr, result: &mut Vec<&'a SimpleGraph>) {
+    match graph {
+        Intersection(graphs) => {
+            for graph in graphs {
+                simple_leaves(graph, result);
+            }
+        }
+        Union(g1, g2) | Difference(g1, g2) => {
+            leaves(g1, result);
+            leaves(g2, result);
+        }
+    }
+}
+
+fn simple_leaves<'a>(graph: &'a SimpleGraph, result: &mut Vec<&'a SimpleGraph>) {
+    match graph {
+        Reverse(graph) | Restricted(graph, _) => simple_leaves(graph, result),
+        Nested(expr) => leaves(expr, result),
+        leaf => result.push(leaf),
+    }
+}
+
+/// Patterns of the queries finding the vertices of graph ends.
+fn query_patterns<'a>(vertices: &'a Vertices, patterns: &mut Vec<&'a Pattern>) {
+    match vertices {
+        Reachable(from, pattern) => {
+            query_patterns(from, patterns);
+            patterns.push(pattern);
+        }
+        Of(source) => {
+            if let Apply(pattern, _) = source.as_ref() {
+                patterns.push(pattern);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Ranges of a vertex set, with the ones of the graphs its vertices are found in.
+fn vertex_ranges(vertices: &Vertices, ranges: &mut Vec<(usize, usize)>) {
+    match vertices {
+        Range { from, to } => ranges.push((*from, *to)),
+        Reachable(from, _) => vertex_ranges(from, ranges),
+        Of(source) => {
+            let mut graphs = Vec::new();
+            leaves(parts(source).1, &mut graphs);
+            for graph in graphs {
+                if let WithEnds {
+                    initials, finals, ..
+                } = graph
+                {
+                    vertex_ranges(initials, ranges);
+                    vertex_ranges(finals, ranges);
+                }
+            }
+        }
+        Set(_) | EmptySet => {}
+    }
+}
+
+/// Ranges of the `begin in` and `end in` conditions of a list.
+fn list_ranges(list: &ListExpr, ranges: &mut Vec<(usize, usize)>) {
+    if let Filter(list, condition) = list {
+        list_ranges(list, ranges);
+        bool_ranges(condition, ranges);
+    }
+}
+
+fn bool_ranges(condition: &BoolExpr, ranges: &mut Vec<(usize, usize)>) {
+    match condition {
+        BeginIn(vertices) | EndIn(vertices) => vertex_ranges(vertices, ranges),
+        And(b1, b2) | Or(b1, b2) => {
+            bool_ranges(b1, ranges);
+            bool_ranges(b2, ranges);
+        }
+        Not(b) => bool_ranges(b, ranges),
+        _ => {}
+    }
+}
+
+fn undefined<F: FnMut(ProblemKind)>(
+    pattern: &Pattern,
+    definitions: &HashSet<&str>,
+    report: &mut F,
+) {
+    match pattern {
+        Term(_) | Inverse(_) => {}
+        Var(name) if name == "epsilon" || definitions.contains(name.as_str()) => {}
+        Var(name) => report(ProblemKind::UndefinedVariable(name.clone())),
+        AnyLabel | NotLabels(_) => {}
+        Star(p) | Plus(p) | Maybe(p) | Repeat(p, ..) => undefined(p, definitions, report),
+        Alt(p1, p2) => {
+            undefined(p1, definitions, report);
+            undefined(p2, definitions, report);
+        }
+        Seq(ps) => ps.iter().for_each(|p| undefined(p, definitions, report)),
+        Call(name, ps) => {
+            if !definitions.contains(name.as_str()) {
+                report(ProblemKind::UndefinedVariable(name.clone()));
+            }
+            ps.iter().for_each(|p| undefined(p, definitions, report))
+        }
+    }
+}
+
+fn end_conditions(list: &ListExpr, conditions: &mut Vec<String>) {
+    if let Filter(list, condition) = list {
+        end_conditions(list, conditions);
+        bool_end_conditions(condition, conditions);
+    }
+}
+
+fn bool_end_conditions(condition: &BoolExpr, conditions: &mut Vec<String>) {
+    let variant = |v: &VertexVariant| match v {
+        VertexVariant::Initial => "initial",
+        VertexVariant::Final => "final",
+    };
+    match condition {
+        LabelIs(_) | LabelIn(_) | LabelMatches(_) => {}
+        BeginIn(_) | EndIn(_) | BeginCompare(..) | EndCompare(..) | SelfLoop => {}
+        BeginIs(v) => conditions.push(format!("begin is {}", variant(v))),
+        EndIs(v) => conditions.push(format!("end is {}", variant(v))),
+        VertexIs(v) => conditions.push(format!("vertex is {}", variant(v))),
+        And(b1, b2) | Or(b1, b2) => {
+            bool_end_conditions(b1, conditions);
+            bool_end_conditions(b2, conditions);
+        }
+        Not(b) => bool_end_conditions(b, conditions),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::syntax::build_ast;
+
+    use super::*;
+
+    fn kinds(text: &str) -> Result<Vec<(usize, ProblemKind)>> {
+        Ok(lint(&build_ast(text)?)
+            .into_iter()
+            .map(|p| (p.statement, p.kind))
+            .collect())
+    }
+
+    #[test]
+    fn valid() -> Result<()> {
+        let problems = kinds(concat!(
+            "connect to db\n",
+            "define s as \"a\" t | epsilon\n",
+            "define t as s \"b\"\n",
+            "get edges which satisfy begin is initial from application of s to g with initials as [0..2]\n",
+        ))?;
+        assert!(problems.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn problems() -> Result<()> {
+        let problems = kinds(concat!(
+            "get edges from g\n",
+            "define a as b\n",
+            "define a as \"a\"\n",
+            "connect to db\n",
+            "get edges from application of a c to g & h with finals as [3..1]\n",
+            "get count of edges which satisfy label is \"a\" and not end is final from g\n",
+            "define c as \"c\"\n",
+        ))?;
+        assert_eq!(
+            vec![
+                (1, ProblemKind::GraphBeforeConnect("g".to_string())),
+                (2, ProblemKind::UndefinedVariable("b".to_string())),
+                (3, ProblemKind::Redefinition("a".to_string())),
+                (5, ProblemKind::UndefinedVariable("c".to_string())),
+                (5, ProblemKind::DescendingRange { from: 3, to: 1 }),
+                (6, ProblemKind::TrivialEnds("end is final".to_string())),
+            ],
+            problems
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn macros() -> Result<()> {
+        let problems = kinds(concat!(
+            "define within(p, q) as p* q x\n",
+            "connect to db\n",
+            "get edges from application of within(\"a\", y) to g\n",
+            "get edges from application of around(within) to g\n",
+            "define within as \"a\"\n",
+        ))?;
+        assert_eq!(
+            vec![
+                (1, ProblemKind::UndefinedVariable("x".to_string())),
+                (3, ProblemKind::UndefinedVariable("y".to_string())),
+                (4, ProblemKind::UndefinedVariable("around".to_string())),
+                (5, ProblemKind::Redefinition("within".to_string())),
+            ],
+            problems
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn includes() -> Result<()> {
+        let problems = kinds(concat!(
+            "get edges from application of a to g\n",
+            "define b as a c\n",
+            "include \"defs.q\"\n",
+            "get edges from application of b d to g\n",
+        ))?;
+        assert_eq!(
+            vec![
+                (1, ProblemKind::UndefinedVariable("a".to_string())),
+                (1, ProblemKind::GraphBeforeConnect("g".to_string())),
+            ],
+            problems
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn graph_definitions() -> Result<()> {
+        let problems = kinds(concat!(
+            "define big as graph g & h with finals as [3..1]\n",
+            "connect to db\n",
+            "get edges which satisfy begin is initial from big\n",
+            "define big as graph g\n",
+        ))?;
+        assert_eq!(
+            vec![
+                (1, ProblemKind::GraphBeforeConnect("g".to_string())),
+                (1, ProblemKind::DescendingRange { from: 3, to: 1 }),
+                (4, ProblemKind::Redefinition("big".to_string())),
+            ],
+            problems
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn vertex_queries() -> Result<()> {
+        let problems = kinds(concat!(
+            "connect to db\n",
+            "define s as \"a\"\n",
+            "get edges from g with initials as (vertices reachable from [0] by x)\n",
+            "    and finals as (vertices of application of s y to h)\n",
+        ))?;
+        assert_eq!(
+            vec![
+                (3, ProblemKind::UndefinedVariable("x".to_string())),
+                (3, ProblemKind::UndefinedVariable("y".to_string())),
+            ],
+            problems
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn descending_ranges() -> Result<()> {
+        let problems = kinds(concat!(
+            "connect to db\n",
+            "get edges which satisfy begin in [3..1] from g\n",
+            "get edges which satisfy label is \"a\" or not end in [5..4] from g\n",
+            "get edges from g with initials as (vertices reachable from [2..0] by \"a\")\n",
+            "get edges from g with finals as (vertices of h with initials as [7..6])\n",
+            "get edges which satisfy begin in [1..3] from g with initials as [0..0]\n",
+        ))?;
+        let range = |from, to| ProblemKind::DescendingRange { from, to };
+        assert_eq!(
+            vec![
+                (2, range(3, 1)),
+                (3, range(5, 4)),
+                (4, range(2, 0)),
+                (5, range(7, 6)),
+            ],
+            problems
+        );
+        Ok(())
+    }
+}
@@ -1,22 +1,38 @@
 use std::str::from_utf8;
 
 use anyhow::Result;
+use lalrpop_util::ParseError;
 
 pub use dot::to_dot;
+pub use error::SyntaxError;
+pub use lint::lint;
+pub use pretty::to_text;
 
 use crate::compute::cfg::ContextFreeGrammar;
 use crate::syntax::ast::{Script, Statement};
 
 pub mod ast;
 pub mod dot;
+mod error;
+pub mod lint;
+mod pretty;
 
 fn input_map(c: char) -> char {
-    if c.is_whitespace() {
+    if c == '\n' {
+        '`'
+    } else if c.is_whitespace() {
         '!'
     } else if c == '.' {
         '@'
     } else if c == '|' {
         '^'
+    } else if c == '!' {
+        '~'
+    } else if c == '^' {
+        ';'
+    } else if c.is_uppercase() || !c.is_ascii() {
+        // Stands for any character of a string literal, the grammar has no other use for them
+        '%'
     } else {
         c
     }
@@ -24,7 +40,16 @@ fn input_map(c: char) -> char {
 
 pub fn check(text: &str) -> Result<bool> {
     let cfg = ContextFreeGrammar::_from_text(from_utf8(include_bytes!("syntax.cfg"))?)?;
-    let chars: Vec<String> = text.chars().map(input_map).map(String::from).collect();
+    // A name or a keyword ends right before `(`, the grammar takes a space there
+    let mut chars: Vec<String> = Vec::with_capacity(text.len());
+    let mut previous = ' ';
+    for c in text.chars() {
+        if c == '(' && (previous.is_alphanumeric() || previous == '_') {
+            chars.push(input_map(' ').to_string());
+        }
+        chars.push(input_map(c).to_string());
+        previous = c;
+    }
     let refs: Vec<&String> = chars.iter().collect();
     Ok(cfg.cyk(refs.as_slice()))
 }
@@ -33,12 +58,18 @@ lalrpop_mod!(parser, "/syntax/parser.rs");
 
 pub fn build_ast(text: &str) -> Result<Script> {
     let parser = parser::scriptParser::new();
-    Ok(parser.parse(text).unwrap())
+    parser.parse(text).map_err(|e| SyntaxError::new(text, e).into())
 }
 
 pub fn parse_statement(text: &str) -> Result<Statement> {
     let parser = parser::statementParser::new();
-    Ok(parser.parse(text).unwrap())
+    parser.parse(text).map_err(|e| SyntaxError::new(text, e).into())
+}
+
+/// Whether `text` is an unfinished statement that more input could complete.
+pub fn is_incomplete(text: &str) -> bool {
+    let parser = parser::statementParser::new();
+    matches!(parser.parse(text), Err(ParseError::UnrecognizedEOF { .. }))
 }
 
 #[cfg(test)]
@@ -68,12 +99,41 @@ mod tests {
     test!("cond", true);
     test!("precedence", true);
     test!("complex", true);
+    test!("objects", true);
+    test!("algebra", true);
+    test!("predicates", true);
+    test!("save", true);
+    test!("strings", true);
+    test!("comments", true);
+    test!("repeat", true);
+    test!("inverse", true);
+    test!("macros", true);
+    test!("keyword_parens", true);
+    test!("groups", true);
+    test!("explain", true);
+    test!("catalog", true);
+    test!("graph_defs", true);
+    test!("vertex_queries", true);
 
     test!("inv_empty", false);
     test!("inv_open", false);
     test!("inv_let", false);
     test!("inv_cond", false);
     test!("inv_precedence", false);
+    test!("inv_objects", false);
+    test!("inv_algebra", false);
+    test!("inv_predicates", false);
+    test!("inv_save", false);
+    test!("inv_strings", false);
+    test!("inv_comments", false);
+    test!("inv_repeat", false);
+    test!("inv_inverse", false);
+    test!("inv_macros", false);
+    test!("inv_groups", false);
+    test!("inv_explain", false);
+    test!("inv_catalog", false);
+    test!("inv_graph_defs", false);
+    test!("inv_vertex_queries", false);
 }
 
 #[cfg(test)]
@@ -136,4 +196,545 @@ mod test_ast {
             Direct(Intersection(vec!(GraphName("g".to_string()))))
         )))
     );
+    test!(
+        "objects",
+        Sequence(vec!(
+            Get(
+                List(Filter(
+                    Box::new(VertexList),
+                    Or(
+                        Box::new(VertexIs(VertexVariant::Initial)),
+                        Box::new(Not(Box::new(VertexIs(VertexVariant::Final))))
+                    )
+                )),
+                Direct(Intersection(vec!(GraphName("g".to_string()))))
+            ),
+            Get(
+                Count(Filter(Box::new(Labels), LabelIs("a".to_string()))),
+                Direct(Intersection(vec!(GraphName("g".to_string()))))
+            ),
+            Get(
+                List(Filter(
+                    Box::new(Pairs),
+                    And(
+                        Box::new(BeginIs(VertexVariant::Initial)),
+                        Box::new(EndIs(VertexVariant::Final))
+                    )
+                )),
+                Apply(
+                    Term("a".to_string()),
+                    Intersection(vec!(GraphName("g".to_string())))
+                )
+            ),
+        ))
+    );
+    test!(
+        "algebra",
+        Sequence(vec!(
+            Connect(vec!("db".to_string())),
+            Get(
+                List(Edges),
+                Direct(Difference(
+                    Box::new(Union(
+                        Box::new(Intersection(vec!(GraphName("graph1".to_string())))),
+                        Box::new(Intersection(vec!(GraphName("graph2".to_string()))))
+                    )),
+                    Box::new(Intersection(vec!(Restricted(
+                        Box::new(GraphName("graph1".to_string())),
+                        vec!("b".to_string())
+                    ))))
+                ))
+            ),
+            Get(
+                Count(Edges),
+                Direct(Intersection(vec!(
+                    Reverse(Box::new(WithEnds {
+                        graph: "graph1".to_string(),
+                        initials: Set(vec!(0)),
+                        finals: Range { from: 0, to: 1 }
+                    })),
+                    GraphName("graph2".to_string())
+                )))
+            ),
+            Get(
+                List(Pairs),
+                Apply(
+                    Seq(vec!(Term("a".to_string()), Term("b".to_string()))),
+                    Intersection(vec!(Restricted(
+                        Box::new(Reverse(Box::new(GraphName("brackets".to_string())))),
+                        vec!("a".to_string(), "b".to_string())
+                    )))
+                )
+            ),
+            Get(
+                List(Labels),
+                Direct(Difference(
+                    Box::new(Union(
+                        Box::new(Intersection(vec!(GraphName("graph1".to_string())))),
+                        Box::new(Intersection(vec!(GraphName("graph2".to_string()))))
+                    )),
+                    Box::new(Intersection(vec!(Reverse(Box::new(GraphName(
+                        "graph1".to_string()
+                    ))))))
+                ))
+            ),
+        ))
+    );
+    test!(
+        "predicates",
+        Sequence(vec!(
+            Get(
+                List(Filter(
+                    Box::new(Edges),
+                    Or(
+                        Box::new(And(
+                            Box::new(BeginCompare(Comparison::Less, 100)),
+                            Box::new(EndIn(Range { from: 3, to: 9 }))
+                        )),
+                        Box::new(SelfLoop)
+                    )
+                )),
+                Direct(Intersection(vec!(GraphName("g".to_string()))))
+            ),
+            Get(
+                Count(Filter(
+                    Box::new(Edges),
+                    And(
+                        Box::new(LabelIn(vec!("a".to_string(), "b".to_string()))),
+                        Box::new(Not(Box::new(LabelMatches("rdf_.*".to_string()))))
+                    )
+                )),
+                Direct(Intersection(vec!(GraphName("g".to_string()))))
+            ),
+            Get(
+                List(Filter(
+                    Box::new(Pairs),
+                    And(
+                        Box::new(And(
+                            Box::new(BeginCompare(Comparison::GreaterOrEqual, 2)),
+                            Box::new(EndCompare(Comparison::NotEqual, 4))
+                        )),
+                        Box::new(EndIn(Set(vec!(1, 2))))
+                    )
+                )),
+                Direct(Intersection(vec!(GraphName("g".to_string()))))
+            ),
+            Get(
+                List(Filter(
+                    Box::new(Labels),
+                    Or(
+                        Box::new(LabelMatches("(a|b)+".to_string())),
+                        Box::new(LabelIn(Vec::new()))
+                    )
+                )),
+                Direct(Intersection(vec!(GraphName("g".to_string()))))
+            ),
+        ))
+    );
+    test!(
+        "save",
+        Sequence(vec!(
+            Connect(vec!("db".to_string())),
+            Define(
+                "ab".to_string(),
+                Seq(vec!(Term("a".to_string()), Term("b".to_string())))
+            ),
+            Save(
+                List(Filter(Box::new(Edges), LabelIs("a".to_string()))),
+                Direct(Intersection(vec!(GraphName("g".to_string())))),
+                "out/edges.csv".to_string()
+            ),
+            Save(
+                Count(Pairs),
+                Apply(
+                    Var("ab".to_string()),
+                    Intersection(vec!(
+                        GraphName("g".to_string()),
+                        GraphName("h".to_string())
+                    ))
+                ),
+                "out/count".to_string()
+            ),
+            SaveGraph(
+                Direct(Difference(
+                    Box::new(Intersection(vec!(GraphName("g".to_string())))),
+                    Box::new(Intersection(vec!(GraphName("h".to_string()))))
+                )),
+                "out/g_h".to_string()
+            ),
+            SaveGraph(
+                Apply(
+                    Var("ab".to_string()),
+                    Intersection(vec!(Reverse(Box::new(GraphName("g".to_string())))))
+                ),
+                "out/ab".to_string()
+            ),
+        ))
+    );
+    test!(
+        "strings",
+        Sequence(vec!(
+            Define(
+                "part".to_string(),
+                Alt(
+                    Box::new(Seq(vec!(
+                        Term("has part".to_string()),
+                        Plus(Box::new(Term("rdf:type".to_string())))
+                    ))),
+                    Box::new(Term("subClassOf^-1".to_string()))
+                )
+            ),
+            Get(
+                List(Filter(
+                    Box::new(Edges),
+                    Or(
+                        Box::new(LabelIs("a\"b\\c".to_string())),
+                        Box::new(LabelIn(vec!("часть".to_string(), "x\ty".to_string())))
+                    )
+                )),
+                Direct(Intersection(vec!(GraphName("g".to_string()))))
+            ),
+        ))
+    );
+    test!(
+        "comments",
+        Sequence(vec!(
+            Include("lib/defs.q".to_string()),
+            Define(
+                "ab".to_string(),
+                Seq(vec!(Term("a".to_string()), Term("b".to_string())))
+            ),
+            Get(
+                Count(Edges),
+                Direct(Difference(
+                    Box::new(Intersection(vec!(GraphName("g".to_string())))),
+                    Box::new(Intersection(vec!(GraphName("h".to_string()))))
+                ))
+            ),
+            Get(
+                List(Filter(Box::new(Edges), LabelIs("a -- # b".to_string()))),
+                Direct(Intersection(vec!(GraphName("g".to_string()))))
+            ),
+        ))
+    );
+    test!(
+        "repeat",
+        Sequence(vec!(
+            Define(
+                "hops".to_string(),
+                Alt(
+                    Box::new(Seq(vec!(
+                        AnyLabel,
+                        Repeat(Box::new(Term("a".to_string())), 2, Some(2))
+                    ))),
+                    Box::new(Repeat(
+                        Box::new(NotLabels(vec!("b".to_string(), "c".to_string()))),
+                        1,
+                        Some(3)
+                    ))
+                )
+            ),
+            Get(
+                List(Pairs),
+                Apply(
+                    Seq(vec!(
+                        Repeat(
+                            Box::new(Seq(vec!(Var("hops".to_string()), Term("b".to_string())))),
+                            0,
+                            None
+                        ),
+                        Maybe(Box::new(AnyLabel))
+                    )),
+                    Intersection(vec!(GraphName("g".to_string())))
+                )
+            ),
+            Get(
+                Count(Filter(
+                    Box::new(Edges),
+                    BeginCompare(Comparison::NotEqual, 0)
+                )),
+                Apply(
+                    Repeat(Box::new(NotLabels(Vec::new())), 2, None),
+                    Intersection(vec!(GraphName("g".to_string())))
+                )
+            ),
+        ))
+    );
+    test!(
+        "inverse",
+        Sequence(vec!(
+            Define(
+                "sibling".to_string(),
+                Seq(vec!(
+                    Term("parent".to_string()),
+                    Inverse("parent".to_string())
+                ))
+            ),
+            Define(
+                "same_generation".to_string(),
+                Alt(
+                    Box::new(Seq(vec!(
+                        Term("parent".to_string()),
+                        Var("same_generation".to_string()),
+                        Inverse("parent".to_string())
+                    ))),
+                    Box::new(Var("sibling".to_string()))
+                )
+            ),
+            Get(
+                List(Pairs),
+                Apply(
+                    Seq(vec!(
+                        Inverse("a".to_string()),
+                        Inverse("b".to_string()),
+                        Inverse("a^-1".to_string())
+                    )),
+                    Intersection(vec!(GraphName("g".to_string())))
+                )
+            ),
+        ))
+    );
+    test!(
+        "macros",
+        Sequence(vec!(
+            Macro(
+                "within".to_string(),
+                vec!("p".to_string(), "q".to_string()),
+                Seq(vec!(
+                    Star(Box::new(Var("p".to_string()))),
+                    Var("q".to_string())
+                ))
+            ),
+            Macro(
+                "twice".to_string(),
+                vec!("p".to_string()),
+                Seq(vec!(Var("p".to_string()), Var("p".to_string())))
+            ),
+            Define(
+                "path".to_string(),
+                Seq(vec!(
+                    Call(
+                        "within".to_string(),
+                        vec!(
+                            Alt(
+                                Box::new(Term("a".to_string())),
+                                Box::new(Term("b".to_string()))
+                            ),
+                            Call("twice".to_string(), vec!(Var("x".to_string())))
+                        )
+                    ),
+                    Call(
+                        "within".to_string(),
+                        vec!(AnyLabel, Term("c".to_string()))
+                    )
+                ))
+            ),
+            Get(
+                List(Pairs),
+                Apply(
+                    Call(
+                        "twice".to_string(),
+                        vec!(Call(
+                            "within".to_string(),
+                            vec!(Inverse("a".to_string()), Var("path".to_string()))
+                        ))
+                    ),
+                    Intersection(vec!(GraphName("g".to_string())))
+                )
+            ),
+        ))
+    );
+
+    test!(
+        "groups",
+        Sequence(vec!(
+            Get(
+                CountBy(Edges, Label),
+                Direct(Intersection(vec!(GraphName("g".to_string()))))
+            ),
+            Get(
+                CountBy(
+                    Filter(Box::new(Edges), BeginCompare(Comparison::Less, 3)),
+                    Begin
+                ),
+                Direct(Intersection(vec!(GraphName("g".to_string()))))
+            ),
+            Get(
+                Distinct(End, Pairs),
+                Apply(
+                    Plus(Box::new(Term("a".to_string()))),
+                    Intersection(vec!(GraphName("g".to_string())))
+                )
+            ),
+        ))
+    );
+
+    test!(
+        "keyword_parens",
+        Sequence(vec!(
+            Get(
+                List(Filter(
+                    Box::new(Edges),
+                    Not(Box::new(LabelIs("a".to_string())))
+                )),
+                Direct(Intersection(vec!(Reverse(Box::new(GraphName(
+                    "g".to_string()
+                ))))))
+            ),
+            Get(
+                List(Pairs),
+                Apply(
+                    Star(Box::new(Term("a".to_string()))),
+                    Union(
+                        Box::new(Intersection(vec!(GraphName("g".to_string())))),
+                        Box::new(Intersection(vec!(GraphName("h".to_string()))))
+                    )
+                )
+            ),
+        ))
+    );
+
+    test!(
+        "explain",
+        Sequence(vec!(
+            Explain(
+                Count(Filter(Box::new(Edges), LabelIs("a".to_string()))),
+                Direct(Intersection(vec!(
+                    GraphName("g".to_string()),
+                    Restricted(
+                        Box::new(GraphName("h".to_string())),
+                        vec!("a".to_string())
+                    )
+                )))
+            ),
+            Explain(
+                List(Pairs),
+                Apply(
+                    Var("s".to_string()),
+                    Intersection(vec!(GraphName("g".to_string())))
+                )
+            ),
+        ))
+    );
+
+    test!(
+        "catalog",
+        Sequence(vec!(
+            Connect(vec!("db".to_string(), "graphs".to_string())),
+            ListGraphs,
+        ))
+    );
+
+    test!(
+        "graph_defs",
+        Sequence(vec!(
+            DefineGraph(
+                "big".to_string(),
+                Intersection(vec!(
+                    GraphName("g".to_string()),
+                    WithEnds {
+                        graph: "h".to_string(),
+                        initials: Set(vec!(0)),
+                        finals: Range { from: 1, to: 9 }
+                    }
+                ))
+            ),
+            Get(
+                List(Edges),
+                Direct(Intersection(vec!(GraphName("big".to_string()))))
+            ),
+        ))
+    );
+
+    test!(
+        "vertex_queries",
+        Sequence(vec!(Get(
+            List(Edges),
+            Direct(Intersection(vec!(WithEnds {
+                graph: "g".to_string(),
+                initials: Reachable(
+                    Box::new(Set(vec!(0))),
+                    Star(Box::new(Term("a".to_string())))
+                ),
+                finals: Of(Box::new(Apply(
+                    Var("s".to_string()),
+                    Intersection(vec!(WithEnds {
+                        graph: "h".to_string(),
+                        initials: Set(vec!(1)),
+                        finals: Set(vec!(2))
+                    }))
+                )))
+            })))
+        )))
+    );
+
+    #[test]
+    fn test_inv_objects() {
+        assert!(build_ast(from_utf8(include_bytes!("../../test_data/scripts/inv_objects")).unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_inv_algebra() {
+        assert!(build_ast(from_utf8(include_bytes!("../../test_data/scripts/inv_algebra")).unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_inv_predicates() {
+        assert!(build_ast(from_utf8(include_bytes!("../../test_data/scripts/inv_predicates")).unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_inv_save() {
+        assert!(build_ast(from_utf8(include_bytes!("../../test_data/scripts/inv_save")).unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_inv_strings() {
+        assert!(build_ast(from_utf8(include_bytes!("../../test_data/scripts/inv_strings")).unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_inv_comments() {
+        assert!(build_ast(from_utf8(include_bytes!("../../test_data/scripts/inv_comments")).unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_inv_repeat() {
+        assert!(build_ast(from_utf8(include_bytes!("../../test_data/scripts/inv_repeat")).unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_inv_inverse() {
+        assert!(build_ast(from_utf8(include_bytes!("../../test_data/scripts/inv_inverse")).unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_inv_macros() {
+        assert!(build_ast(from_utf8(include_bytes!("../../test_data/scripts/inv_macros")).unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_inv_groups() {
+        assert!(build_ast(from_utf8(include_bytes!("../../test_data/scripts/inv_groups")).unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_inv_explain() {
+        assert!(build_ast(from_utf8(include_bytes!("../../test_data/scripts/inv_explain")).unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_inv_catalog() {
+        assert!(build_ast(from_utf8(include_bytes!("../../test_data/scripts/inv_catalog")).unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_inv_graph_defs() {
+        assert!(build_ast(from_utf8(include_bytes!("../../test_data/scripts/inv_graph_defs")).unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_inv_vertex_queries() {
+        assert!(build_ast(from_utf8(include_bytes!("../../test_data/scripts/inv_vertex_queries")).unwrap()).is_err());
+    }
 }
@@ -0,0 +1,549 @@
+use std::fmt::{Display, Formatter, Result};
+
+use crate::compute::string::literal;
+
+use super::ast::*;
+
+/// Prints a script in the canonical layout: one statement per line,
+/// filters of a `get` and its source on separate indented lines.
+/// Parsing the text gives back an equal script.
+pub fn to_text(s: &Script) -> String {
+    let Sequence(statements) = s;
+    statements.iter().map(|s| format!("{}\n", s)).collect()
+}
+
+impl Display for Statement {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Connect(path) => write!(f, "connect to {}", path.join(".")),
+            Define(name, pattern) => write!(f, "define {} as {}", name, pattern),
+            DefineGraph(name, graph) => write!(f, "define {} as graph {}", name, graph),
+            Macro(name, parameters, pattern) => write!(
+                f,
+                "define {}({}) as {}",
+                name,
+                parameters.join(", "),
+                pattern
+            ),
+            Get(object, source) => write!(f, "get {}", Query(object, source)),
+            Explain(object, source) => write!(f, "explain get {}", Query(object, source)),
+            Save(object, source, file) => {
+                write!(f, "save {} to {}", Query(object, source), literal(file))
+            }
+            SaveGraph(source, file) => write!(f, "save graph {} to {}", source, literal(file)),
+            Include(file) => write!(f, "include {}", literal(file)),
+            ListGraphs => write!(f, "list graphs"),
+        }
+    }
+}
+
+/// `obj_expr from source` of `get` and `save` statements.
+struct Query<'a>(&'a ObjectExpr, &'a Source);
+
+impl Display for Query<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let (list, group) = match self.0 {
+            Count(list) => {
+                write!(f, "count of ")?;
+                (list, None)
+            }
+            CountBy(list, key) => {
+                write!(f, "count of ")?;
+                (list, Some(key))
+            }
+            Distinct(key, list) => {
+                write!(f, "distinct {}s of ", key)?;
+                (list, None)
+            }
+            List(list) => (list, None),
+        };
+        let separator = match list {
+            Filter(..) => "\n    ",
+            _ => " ",
+        };
+        write!(f, "{}", list)?;
+        if let Some(key) = group {
+            write!(f, "{}group by {}", separator, key)?;
+        }
+        write!(f, "{}from {}", separator, self.1)
+    }
+}
+
+impl Display for GroupKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Label => write!(f, "label"),
+            Begin => write!(f, "begin"),
+            End => write!(f, "end"),
+        }
+    }
+}
+
+impl Display for ListExpr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Edges => write!(f, "edges"),
+            VertexList => write!(f, "vertices"),
+            Labels => write!(f, "labels"),
+            Pairs => write!(f, "pairs"),
+            Filter(list, condition) => write!(f, "{}\n    which satisfy {}", list, condition),
+        }
+    }
+}
+
+impl Display for Source {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Apply(pattern, graph) => write!(f, "application of {} to {}", pattern, graph),
+            Direct(graph) => write!(f, "{}", graph),
+        }
+    }
+}
+
+impl Display for GraphExpr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Intersection(graphs) => {
+                for (i, graph) in graphs.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " & ")?;
+                    }
+                    write!(f, "{}", graph)?;
+                }
+                Ok(())
+            }
+            Union(g1, g2) => write!(f, "{} | {}", g1, Operand(g2)),
+            Difference(g1, g2) => write!(f, "{} - {}", g1, Operand(g2)),
+        }
+    }
+}
+
+/// Right operand of `|` and `-`, which binds like an intersection.
+struct Operand<'a>(&'a GraphExpr);
+
+impl Display for Operand<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self.0 {
+            Intersection(_) => write!(f, "{}", self.0),
+            _ => write!(f, "({})", self.0),
+        }
+    }
+}
+
+impl Display for SimpleGraph {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            GraphName(name) => write!(f, "{}", name),
+            WithEnds {
+                initials,
+                finals,
+                graph,
+            } => {
+                let omitted = |v: &Vertices| *v == Set(Vec::new());
+                match (omitted(initials), omitted(finals)) {
+                    (false, true) => write!(f, "{} with initials as {}", graph, initials),
+                    (true, false) => write!(f, "{} with finals as {}", graph, finals),
+                    _ => write!(
+                        f,
+                        "{} with initials as {} and finals as {}",
+                        graph, initials, finals
+                    ),
+                }
+            }
+            Reverse(graph) => match graph.as_ref() {
+                Restricted(..) => write!(f, "reverse ({})", graph),
+                _ => write!(f, "reverse {}", graph),
+            },
+            Restricted(graph, labels) => {
+                write!(f, "{} restricted to labels {}", graph, quoted(labels))
+            }
+            Nested(expr) => write!(f, "({})", expr),
+        }
+    }
+}
+
+impl Display for Vertices {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Set(set) => {
+                let set: Vec<String> = set.iter().map(|v| v.to_string()).collect();
+                write!(f, "[{}]", set.join(", "))
+            }
+            Range { from, to } => write!(f, "[{}..{}]", from, to),
+            EmptySet => write!(f, "[]"),
+            Reachable(from, pattern) => {
+                write!(f, "(vertices reachable from {} by {})", from, pattern)
+            }
+            Of(source) => write!(f, "(vertices of {})", source),
+        }
+    }
+}
+
+fn quoted(labels: &[String]) -> String {
+    let labels: Vec<String> = labels.iter().map(|l| literal(l)).collect();
+    format!("[{}]", labels.join(", "))
+}
+
+impl Display for VertexVariant {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            VertexVariant::Initial => write!(f, "initial"),
+            VertexVariant::Final => write!(f, "final"),
+        }
+    }
+}
+
+impl Display for Comparison {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let text = match self {
+            Comparison::Less => "<",
+            Comparison::LessOrEqual => "<=",
+            Comparison::Greater => ">",
+            Comparison::GreaterOrEqual => ">=",
+            Comparison::Equal => "=",
+            Comparison::NotEqual => "!=",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+/// Precedence levels of `parser.lalrpop`, an operand of a lower level is put in parentheses.
+const OR: u8 = 0;
+const AND: u8 = 1;
+const NOT: u8 = 2;
+
+const ALT: u8 = 0;
+const SEQ: u8 = 1;
+const MANY: u8 = 2;
+
+impl BoolExpr {
+    fn level(&self) -> u8 {
+        match self {
+            Or(..) => OR,
+            And(..) => AND,
+            _ => NOT,
+        }
+    }
+
+    fn fmt_at(&self, f: &mut Formatter<'_>, level: u8) -> Result {
+        if self.level() < level {
+            write!(f, "(")?;
+            self.fmt_at(f, OR)?;
+            return write!(f, ")");
+        }
+        match self {
+            LabelIs(label) => write!(f, "label is {}", literal(label)),
+            LabelIn(labels) => write!(f, "label in {}", quoted(labels)),
+            LabelMatches(pattern) => write!(f, "label matches {}", literal(pattern)),
+            BeginIs(variant) => write!(f, "begin is {}", variant),
+            EndIs(variant) => write!(f, "end is {}", variant),
+            BeginIn(vertices) => write!(f, "begin in {}", vertices),
+            EndIn(vertices) => write!(f, "end in {}", vertices),
+            BeginCompare(comparison, n) => write!(f, "begin {} {}", comparison, n),
+            EndCompare(comparison, n) => write!(f, "end {} {}", comparison, n),
+            SelfLoop => write!(f, "begin = end"),
+            VertexIs(variant) => write!(f, "vertex is {}", variant),
+            And(b1, b2) => {
+                b1.fmt_at(f, AND)?;
+                write!(f, " and ")?;
+                b2.fmt_at(f, NOT)
+            }
+            Or(b1, b2) => {
+                b1.fmt_at(f, OR)?;
+                write!(f, " or ")?;
+                b2.fmt_at(f, AND)
+            }
+            Not(b) => {
+                write!(f, "not ")?;
+                b.fmt_at(f, NOT)
+            }
+        }
+    }
+}
+
+impl Display for BoolExpr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        self.fmt_at(f, OR)
+    }
+}
+
+impl Pattern {
+    /// A sequence of one pattern is the pattern itself, the empty one is always `()`.
+    fn level(&self) -> u8 {
+        match self {
+            Alt(..) => ALT,
+            Seq(ps) if ps.len() == 1 => ps[0].level(),
+            Seq(ps) if !ps.is_empty() => SEQ,
+            _ => MANY,
+        }
+    }
+
+    /// Name of a pattern printed as a bare name.
+    fn name(&self) -> Option<&String> {
+        match self {
+            Var(name) => Some(name),
+            Seq(ps) if ps.len() == 1 => ps[0].name(),
+            _ => None,
+        }
+    }
+
+    /// Whether the pattern is printed starting with `(` as an item of a sequence.
+    fn opens(&self) -> bool {
+        match self {
+            Star(p) | Plus(p) | Maybe(p) | Repeat(p, ..) => p.level() < MANY || p.opens(),
+            Seq(ps) if ps.len() == 1 => ps[0].opens(),
+            Alt(..) | Seq(_) => true,
+            _ => false,
+        }
+    }
+
+    fn fmt_at(&self, f: &mut Formatter<'_>, level: u8) -> Result {
+        if self.level() < level {
+            write!(f, "(")?;
+            self.fmt_at(f, ALT)?;
+            return write!(f, ")");
+        }
+        match self {
+            Term(label) => write!(f, "{}", literal(label)),
+            Inverse(label) => write!(f, "^{}", literal(label)),
+            Var(name) => write!(f, "{}", name),
+            Star(p) => {
+                p.fmt_at(f, MANY)?;
+                write!(f, "*")
+            }
+            Plus(p) => {
+                p.fmt_at(f, MANY)?;
+                write!(f, "+")
+            }
+            Maybe(p) => {
+                p.fmt_at(f, MANY)?;
+                write!(f, "?")
+            }
+            Repeat(p, from, to) => {
+                p.fmt_at(f, MANY)?;
+                match to {
+                    Some(to) if to == from => write!(f, "{{{}}}", from),
+                    Some(to) => write!(f, "{{{},{}}}", from, to),
+                    None => write!(f, "{{{},}}", from),
+                }
+            }
+            AnyLabel => write!(f, "."),
+            NotLabels(labels) => write!(f, "!{}", quoted(labels)),
+            Alt(p1, p2) => {
+                p1.fmt_at(f, ALT)?;
+                write!(f, " | ")?;
+                p2.fmt_at(f, SEQ)
+            }
+            Seq(ps) if ps.is_empty() => write!(f, "()"),
+            Seq(ps) if ps.len() == 1 => ps[0].fmt_at(f, level),
+            Seq(ps) => {
+                for (i, p) in ps.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " ")?;
+                    }
+                    // `name (p)` is a call
+                    let next = ps.get(i + 1);
+                    match p.name() {
+                        Some(name) if next.is_some_and(Pattern::opens) => write!(f, "({})", name)?,
+                        _ => p.fmt_at(f, MANY)?,
+                    }
+                }
+                Ok(())
+            }
+            Call(name, arguments) => {
+                write!(f, "{}(", name)?;
+                for (i, p) in arguments.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    p.fmt_at(f, ALT)?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+impl Display for Pattern {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        self.fmt_at(f, ALT)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::from_utf8;
+
+    use anyhow::Result;
+
+    use crate::syntax::build_ast;
+
+    use super::*;
+
+    fn round_trip(text: &str) -> Result<String> {
+        let script = build_ast(text)?;
+        let formatted = to_text(&script);
+        assert_eq!(script, build_ast(&formatted)?);
+        assert_eq!(formatted, to_text(&build_ast(&formatted)?));
+        Ok(formatted)
+    }
+
+    macro_rules! test {
+        ($script: expr) => {
+            paste::paste! {
+                #[test]
+                fn [<test _ $script>]() -> Result<()> {
+                    round_trip(from_utf8(include_bytes!(concat!("../../test_data/scripts/", $script)))?)?;
+                    Ok(())
+                }
+            }
+        };
+    }
+
+    test!("empty");
+    test!("open");
+    test!("let");
+    test!("get");
+    test!("cond");
+    test!("precedence");
+    test!("complex");
+    test!("objects");
+    test!("algebra");
+    test!("predicates");
+    test!("save");
+    test!("strings");
+    test!("comments");
+    test!("repeat");
+    test!("inverse");
+    test!("macros");
+    test!("groups");
+    test!("explain");
+    test!("catalog");
+    test!("graph_defs");
+    test!("vertex_queries");
+
+    #[test]
+    fn layout() -> Result<()> {
+        let formatted = round_trip(concat!(
+            "connect to db . graphs define a as \"b\"\"c\"* | (d | e?)\n",
+            "get count of edges which satisfy not (label is \"a\" or begin is initial) and end is final\n",
+            "    from application of (a | \"b\")+ () to g & h with finals as [] & f with initials as [1..3]",
+        ))?;
+        assert_eq!(
+            concat!(
+                "connect to db.graphs\n",
+                "define a as \"b\" \"c\"* | (d | e?)\n",
+                "get count of edges\n",
+                "    which satisfy not (label is \"a\" or begin is initial) and end is final\n",
+                "    from application of (a | \"b\")+ () to g & h with finals as [] & f with initials as [1..3]\n",
+            ),
+            formatted
+        );
+
+        let graph = |name: &str| Intersection(vec![GraphName(name.to_string())]);
+        let restricted = Restricted(Box::new(GraphName("g".to_string())), vec!["a".to_string()]);
+        let cases = vec![
+            (
+                Union(
+                    Box::new(graph("a")),
+                    Box::new(Union(Box::new(graph("b")), Box::new(graph("c")))),
+                ),
+                "a | (b | c)",
+            ),
+            (
+                Difference(
+                    Box::new(graph("a")),
+                    Box::new(Difference(Box::new(graph("b")), Box::new(graph("c")))),
+                ),
+                "a - (b - c)",
+            ),
+            (
+                Intersection(vec![Reverse(Box::new(restricted))]),
+                "reverse (g restricted to labels [\"a\"])",
+            ),
+            (
+                Intersection(vec![
+                    GraphName("a".to_string()),
+                    Nested(Box::new(Intersection(vec![
+                        GraphName("b".to_string()),
+                        Reverse(Box::new(Nested(Box::new(Difference(
+                            Box::new(graph("c")),
+                            Box::new(graph("d")),
+                        ))))),
+                    ]))),
+                ]),
+                "a & (b & reverse (c - d))",
+            ),
+        ];
+        for (graph, text) in cases {
+            assert_eq!(text, graph.to_string());
+            let script = Sequence(vec![Get(List(Edges), Direct(graph))]);
+            assert_eq!(script, build_ast(&to_text(&script))?);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn minimal_parentheses() -> Result<()> {
+        let (a, b, c) = (
+            Box::new(Term("a".to_string())),
+            Box::new(Term("b".to_string())),
+            Box::new(Var("c".to_string())),
+        );
+        let cases = vec![
+            (
+                Alt(Box::new(Alt(a.clone(), b.clone())), c.clone()),
+                "\"a\" | \"b\" | c",
+            ),
+            (
+                Alt(a.clone(), Box::new(Alt(b.clone(), c.clone()))),
+                "\"a\" | (\"b\" | c)",
+            ),
+            (
+                Seq(vec![*a.clone(), Seq(vec![*b.clone(), *c.clone()])]),
+                "\"a\" (\"b\" c)",
+            ),
+            (
+                Star(Box::new(Seq(vec![*a.clone(), *b.clone()]))),
+                "(\"a\" \"b\")*",
+            ),
+            (Maybe(Box::new(Plus(c.clone()))), "c+?"),
+            (
+                Seq(vec![
+                    *c.clone(),
+                    Call("f".to_string(), vec![Alt(a.clone(), b.clone()), *c.clone()]),
+                ]),
+                "c f(\"a\" | \"b\", c)",
+            ),
+            (
+                Seq(vec![*c.clone(), Alt(a.clone(), b.clone()), *c.clone()]),
+                "(c) (\"a\" | \"b\") c",
+            ),
+            (
+                Seq(vec![*c.clone(), Star(Box::new(Seq(vec![]))), Plus(c.clone())]),
+                "(c) ()* c+",
+            ),
+            (
+                Repeat(Box::new(Seq(vec![*a.clone(), *c.clone()])), 1, None),
+                "(\"a\" c){1,}",
+            ),
+            (
+                Seq(vec![
+                    AnyLabel,
+                    Repeat(Box::new(NotLabels(vec![])), 0, Some(2)),
+                ]),
+                ". ![]{0,2}",
+            ),
+            (
+                Alt(Box::new(Seq(vec![*a, *b])), Box::new(Seq(vec![]))),
+                "\"a\" \"b\" | ()",
+            ),
+        ];
+        for (pattern, text) in cases {
+            assert_eq!(text, pattern.to_string());
+            let script = Sequence(vec![Define("p".to_string(), pattern)]);
+            assert_eq!(script, build_ast(&to_text(&script))?);
+        }
+        Ok(())
+    }
+}